@@ -0,0 +1,131 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use clap::ValueEnum;
+
+use crate::error::{Error, Result};
+
+// Part of the contribution calendar that GitHub shows on the profile
+pub struct CalendarWindow {
+  pub first_day: NaiveDate,
+  pub last_day: NaiveDate,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Align {
+  /// Start in the first week that fits the pattern
+  Left,
+  /// Leave the same number of empty weeks on both sides
+  Center,
+}
+
+fn sunday_before(date: NaiveDate) -> NaiveDate {
+  date - Duration::days(date.weekday().num_days_from_sunday() as i64)
+}
+
+impl CalendarWindow {
+  // calendar of a year selected under the graph, the current one ends today
+  pub fn year(year: i32, today: NaiveDate) -> Option<Self> {
+    let mut last_day = NaiveDate::from_ymd_opt(year, 12, 31)?;
+    if year == today.year() {
+      last_day = today;
+    }
+    Some(CalendarWindow {
+      first_day: NaiveDate::from_ymd_opt(year, 1, 1)?,
+      last_day,
+    })
+  }
+
+  // default view: 52 full weeks before the current one, plus the current week up to today
+  pub fn rolling(today: NaiveDate) -> Self {
+    CalendarWindow {
+      first_day: sunday_before(today) - Duration::weeks(52),
+      last_day: today,
+    }
+  }
+
+  // first column of the calendar, it may start before `first_day`
+  pub fn first_sunday(&self) -> NaiveDate {
+    sunday_before(self.first_day)
+  }
+
+  pub fn columns(&self) -> usize {
+    ((self.last_day - self.first_sunday()).num_days() / 7 + 1) as usize
+  }
+
+  fn contains(&self, start_date: NaiveDate, dots: &[[u8; 7]]) -> bool {
+    dots.iter().enumerate().all(|(column, week)| {
+      week.iter().enumerate().all(|(week_day, level)| {
+        let date = start_date + Duration::days((column * 7 + week_day) as i64);
+        *level == 0 || (self.first_day <= date && date <= self.last_day)
+      })
+    })
+  }
+
+  // Sunday of the first column of the pattern, with empty columns around it dropped
+  pub fn fit(&self, dots: &[[u8; 7]], align: Align) -> Result<(NaiveDate, Vec<[u8; 7]>)> {
+    let is_painted = |week: &[u8; 7]| week.iter().any(|level| *level > 0);
+    let first = dots.iter().position(is_painted).unwrap_or(0);
+    let last = dots.iter().rposition(is_painted).map_or(first, |last| last + 1);
+    let dots = dots[first..last].to_vec();
+
+    let columns = self.columns();
+    if dots.len() > columns {
+      let message = format!(
+        "pattern is {} weeks wide, but the calendar from {} to {} has only {columns} weeks",
+        dots.len(), self.first_day, self.last_day
+      );
+      return Err(Error::Render(message));
+    }
+
+    let free = columns - dots.len();
+    let preferred = match align {
+      Align::Left => 0,
+      Align::Center => free / 2,
+    };
+    // partial weeks at the edges can push the pattern by a column
+    let mut offsets: Vec<usize> = (0..=free).collect();
+    offsets.sort_by_key(|offset| offset.abs_diff(preferred));
+    offsets
+      .into_iter()
+      .map(|offset| self.first_sunday() + Duration::weeks(offset as i64))
+      .find(|start_date| self.contains(*start_date, &dots))
+      .map(|start_date| (start_date, dots))
+      .ok_or_else(|| {
+        let message = format!(
+          "pattern does not fit between {} and {}, some days would land outside of the calendar",
+          self.first_day, self.last_day
+        );
+        Error::Render(message)
+      })
+  }
+
+  // the whole calendar with the pattern painted in it
+  pub fn spread(&self, start_date: NaiveDate, dots: &[[u8; 7]]) -> Vec<[u8; 7]> {
+    let offset = ((start_date - self.first_sunday()).num_days() / 7) as usize;
+    let mut calendar = vec![[0; 7]; self.columns().max(offset + dots.len())];
+    calendar[offset..offset + dots.len()].copy_from_slice(dots);
+    calendar
+  }
+}
+
+#[test]
+fn test_fit_into_year() {
+  // 2022 starts on Saturday, so the first column has a single visible day
+  let window = CalendarWindow::year(2022, NaiveDate::from_ymd(2023, 5, 1)).unwrap();
+  assert_eq!(window.first_sunday(), NaiveDate::from_ymd(2021, 12, 26));
+  assert_eq!(window.columns(), 53);
+
+  let dots = vec![[0; 7], [4, 0, 0, 0, 0, 0, 4], [0; 7]];
+  let (start_date, placed) = window.fit(&dots, Align::Left).unwrap();
+  assert_eq!(placed.len(), 1);
+  assert_eq!(start_date, NaiveDate::from_ymd(2022, 1, 2));
+  let (start_date, _) = window.fit(&dots, Align::Center).unwrap();
+  assert_eq!(start_date, NaiveDate::from_ymd(2021, 12, 26) + Duration::weeks(26));
+
+  assert!(window.fit(&vec![[1; 7]; 54], Align::Left).is_err());
+
+  // the days after today are not in the calendar yet
+  let current = CalendarWindow::year(2022, NaiveDate::from_ymd(2022, 3, 9)).unwrap();
+  assert_eq!(current.last_day, NaiveDate::from_ymd(2022, 3, 9));
+  assert_eq!(current.columns(), 11);
+  assert!(current.fit(&[[1; 7]; 12], Align::Left).is_err());
+}
@@ -0,0 +1,230 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use chrono_tz::Tz;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::calendar::Align;
+use crate::dates::{LevelCommits, MAX_DAY_COMMITS};
+use crate::font::BitmapFont;
+use crate::forge::ForgeKind;
+use crate::git::{GitBackend, Identity};
+use crate::raster::ImageMode;
+
+/// Create commits at specific dates that make your activity graph look like a word
+#[derive(Parser, Debug)]
+#[clap(version, about)]
+pub struct Cli {
+  #[clap(subcommand)]
+  pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+  /// Render the pattern and print the grid without touching git or GitHub
+  Preview {
+    #[clap(flatten)]
+    pattern: PatternArgs,
+    /// Also draw the contribution calendar into this SVG file
+    #[clap(long, value_name = "FILE")]
+    svg: Option<String>,
+    /// Save the grid as a pattern file that can be edited and loaded with --pattern
+    #[clap(long, value_name = "FILE")]
+    save_pattern: Option<String>,
+  },
+  /// Create a local repository with the painted commits
+  Paint {
+    #[clap(flatten)]
+    pattern: PatternArgs,
+    #[clap(flatten)]
+    author: AuthorArgs,
+    #[clap(flatten)]
+    local: LocalArgs,
+    /// Print the grid and the commit dates instead of creating the repository
+    #[clap(long)]
+    dry_run: bool,
+  },
+  /// Paint the commits and push them to a repository on GitHub, GitLab or Gitea
+  Push {
+    #[clap(flatten)]
+    pattern: PatternArgs,
+    #[clap(flatten)]
+    author: AuthorArgs,
+    #[clap(flatten)]
+    local: LocalArgs,
+    /// Print the grid and the commit dates without touching git or GitHub
+    #[clap(long)]
+    dry_run: bool,
+    /// Name of the repository that receives the commits
+    #[clap(long, default_value = "activity-repo")]
+    repo: String,
+    /// Delete the repository if it already exists (asks for confirmation first)
+    #[clap(long, conflicts_with_all = &["append", "orphan"])]
+    replace: bool,
+    /// Do not wait for the contribution calendar to show the painting after the push
+    #[clap(long)]
+    no_verify: bool,
+    #[clap(flatten)]
+    forge: ForgeArgs,
+  },
+  /// Log in to the forge and store the access token
+  Auth {
+    #[clap(flatten)]
+    forge: ForgeArgs,
+  },
+}
+
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("source").required(true).args(&["text", "image", "pattern"])))]
+pub struct PatternArgs {
+  /// Text to draw on the activity graph
+  #[clap(short, long)]
+  pub text: Option<String>,
+  /// PNG picture to draw instead of the text, scaled down to seven rows
+  #[clap(long, value_name = "PNG")]
+  pub image: Option<String>,
+  /// Hand-drawn pattern: 7 lines of '.', '1'-'4' or '#', the format `preview` prints
+  #[clap(long, value_name = "FILE")]
+  pub pattern: Option<String>,
+  /// Which pixels of the picture count as painted
+  #[clap(long, value_enum, default_value = "alpha")]
+  pub image_mode: ImageMode,
+  /// Sunday of the first column, in YYYY-MM-DD format
+  #[clap(short, long, value_parser = parse_date, group = "placement")]
+  pub start_date: Option<NaiveDate>,
+  /// Fit the pattern into the calendar of this year
+  #[clap(long, value_name = "YYYY", group = "placement")]
+  pub year: Option<i32>,
+  /// Fit the pattern into the last 53 weeks, the default view of the profile (default)
+  #[clap(long, group = "placement")]
+  pub rolling: bool,
+  /// Where the pattern goes inside the calendar chosen with --year or --rolling
+  #[clap(long, value_enum, default_value = "center")]
+  pub align: Align,
+  /// Font family used to render the text
+  #[clap(long, default_value = "Source Code Pro")]
+  pub font: String,
+  /// Draw the text with a built-in pixel font instead of the installed --font
+  #[clap(long, value_enum)]
+  pub bitmap_font: Option<BitmapFont>,
+  /// Empty columns between letters of the bitmap font
+  #[clap(long, default_value_t = 1)]
+  pub letter_spacing: usize,
+  /// Commits added per contribution level, a cell of level 3 gets three times as many;
+  /// `push` raises them when real days of the calendar are busier
+  #[clap(short, long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..))]
+  pub intensity: u8,
+  /// Exact commit counts for levels 1 to 4, e.g. 1,5,10,20; overrides --intensity.
+  /// GitHub colours the cells relative to the busiest day of the year, `push` keeps these as they are
+  #[clap(long, value_name = "L1,L2,L3,L4", value_parser = parse_level_commits)]
+  pub level_commits: Option<LevelCommits>,
+  /// Timezone the profile shows the calendar in, like Europe/Berlin; commits are made
+  /// in the morning of this timezone and carry its offset
+  #[clap(long, default_value = "UTC", value_parser = parse_timezone)]
+  pub timezone: Tz,
+}
+
+impl PatternArgs {
+  pub fn level_commits(&self) -> LevelCommits {
+    let intensity = self.intensity as u32;
+    self.level_commits.unwrap_or([intensity, 2 * intensity, 3 * intensity, 4 * intensity])
+  }
+}
+
+#[derive(Args, Debug)]
+pub struct AuthorArgs {
+  /// Author name written into every commit
+  #[clap(long, default_value = "Activity Brush")]
+  pub author_name: String,
+  /// Author email; `push` checks that it is a verified email of the account,
+  /// without it `push` picks the verified primary email or the noreply one
+  #[clap(long, alias = "email")]
+  pub author_email: Option<String>,
+  /// Committer name, the author name when not set
+  #[clap(long)]
+  pub committer_name: Option<String>,
+  /// Committer email, the author email when not set
+  #[clap(long)]
+  pub committer_email: Option<String>,
+}
+
+impl AuthorArgs {
+  // author and committer of every commit, `email` is the resolved author email
+  pub fn identities(&self, email: String) -> (Identity, Identity) {
+    let committer = Identity {
+      name: self.committer_name.clone().unwrap_or_else(|| self.author_name.clone()),
+      email: self.committer_email.clone().unwrap_or_else(|| email.clone()),
+    };
+    let author = Identity { name: self.author_name.clone(), email };
+    (author, committer)
+  }
+}
+
+#[derive(Args, Debug)]
+pub struct LocalArgs {
+  /// Directory where the local repository is created
+  #[clap(long, default_value = "temp_git")]
+  pub dir: String,
+  /// How the commits are written and pushed
+  #[clap(long, value_enum, default_value = "libgit2")]
+  pub git_backend: GitBackend,
+  /// Keep the existing history: open --dir (or clone the remote into it) and add the commits on top
+  #[clap(long)]
+  pub append: bool,
+  /// Like --append, but the commits go to a new branch without history next to the existing ones.
+  /// GitHub counts only commits of the default branch, so `push` cannot verify the painting
+  #[clap(long, value_name = "BRANCH")]
+  pub orphan: Option<String>,
+}
+
+impl LocalArgs {
+  pub fn keeps_history(&self) -> bool {
+    self.append || self.orphan.is_some()
+  }
+}
+
+#[derive(Args, Debug)]
+pub struct ForgeArgs {
+  /// Service that hosts the repository
+  #[clap(long, value_enum, default_value = "github")]
+  pub forge: ForgeKind,
+  /// Self-hosted instance, for example ghe.example.com; defaults to the public instance of the forge
+  #[clap(long)]
+  pub host: Option<String>,
+  /// Client ID of an OAuth app with the device flow, needed for the GitHub login on --host
+  /// (or set ACTIVITY_BRUSH_CLIENT_ID); github.com uses the app of the brush
+  #[clap(long)]
+  pub client_id: Option<String>,
+  /// Keep the painting repository private; GitHub then asks for the full `repo` scope
+  #[clap(long)]
+  pub private: bool,
+}
+
+fn parse_level_commits(value: &str) -> Result<LevelCommits, String> {
+  let counts = value
+    .split(',')
+    .map(|count| count.trim().parse::<u32>().map_err(|e| format!("'{count}' is not a commit count: {e}")))
+    .collect::<Result<Vec<_>, _>>()?;
+  let level_commits: LevelCommits = counts
+    .try_into()
+    .map_err(|_| format!("'{value}' must list exactly four commit counts"))?;
+  if level_commits[0] == 0 || level_commits.windows(2).any(|pair| pair[0] >= pair[1]) {
+    return Err(format!("'{value}' must be increasing positive commit counts"));
+  }
+  if level_commits[3] > MAX_DAY_COMMITS {
+    return Err(format!("'{value}' has more than {MAX_DAY_COMMITS} commits a day, they would not fit into the day"));
+  }
+  Ok(level_commits)
+}
+
+fn parse_timezone(value: &str) -> Result<Tz, String> {
+  value.parse::<Tz>().map_err(|e| format!("{e}, use an IANA name like Europe/Berlin"))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+  let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .map_err(|e| format!("'{value}' is not a YYYY-MM-DD date: {e}"))?;
+  // every column of the activity graph starts on Sunday
+  if date.weekday() != Weekday::Sun {
+    return Err(format!("{value} is a {}, but the first column must start on Sunday", date.weekday()));
+  }
+  Ok(date)
+}
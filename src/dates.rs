@@ -1,11 +1,18 @@
-use chrono::{NaiveDate, NaiveDateTime, Duration};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use crate::forge::Contributions;
+#[cfg(test)]
+use crate::svg::color_level;
 
 // About iterators
 // https://aloso.github.io/2021/03/09/creating-an-iterator
 
+#[allow(dead_code)]
 pub type Week<D> = [Option<D>; 7];
 // type VecOfWeeks<D> = Vec<Week<D>>;
 
+#[allow(dead_code)]
 pub struct VecOfWeeksIter<'a, D> {
   remaining_weeks: &'a [Week<D>],
   week_day: usize,
@@ -25,7 +32,7 @@ impl<'a, D> Iterator for VecOfWeeksIter<'a, D> {
 
   fn next(&mut self) -> Option<Self::Item> {
     let mut value = None;
-    while !value.is_some() && self.remaining_weeks.len() > 0 {
+    while value.is_none() && !self.remaining_weeks.is_empty() {
       value = self.remaining_weeks[0][self.week_day].as_ref();
     
       self.week_day += 1;
@@ -38,9 +45,10 @@ impl<'a, D> Iterator for VecOfWeeksIter<'a, D> {
   }
 }
 
-pub fn wrap_into_iter<'a, D>(data: &'a Vec<[Option<D>; 7]>) -> VecOfWeeksIter<'a, D> {
+#[allow(dead_code)]
+pub fn wrap_into_iter<'a, D>(data: &'a [[Option<D>; 7]]) -> VecOfWeeksIter<'a, D> {
   VecOfWeeksIter {
-    remaining_weeks: &data[..],
+    remaining_weeks: data,
     week_day: 0
   }
 }
@@ -69,45 +77,115 @@ pub fn dots_to_dates(start_date: NaiveDate, dots: Vec<[u8; 7]>) -> Vec<[Option<N
   dates
 }
 
-#[allow(dead_code)]
-pub fn dots_to_dates_flat(start_date: NaiveDate, dots: Vec<[u8; 7]>) -> Vec<NaiveDateTime> {
+// commits made for a cell of level 1, 2, 3 and 4
+pub type LevelCommits = [u32; 4];
+
+// commits of a day go one per second from 09:00:00 and have to stay within the day
+pub const MAX_DAY_COMMITS: u32 = 15 * 60 * 60;
+
+// how many commits a painted cell turns into
+pub fn commit_count(level: u8, level_commits: &LevelCommits) -> u32 {
+  match level {
+    0 => 0,
+    level => level_commits[(level.min(4) - 1) as usize],
+  }
+}
+
+pub fn commits_per_day(dots: &[[u8; 7]], level_commits: &LevelCommits) -> Vec<[u32; 7]> {
+  dots
+    .iter()
+    .map(|week| week.map(|level| commit_count(level, level_commits)))
+    .collect()
+}
+
+pub fn dots_to_dates_flat(start_date: NaiveDate, dots: &[[u8; 7]], level_commits: &LevelCommits, timezone: Tz) -> Vec<DateTime<FixedOffset>> {
+  commits_to_dates(start_date, &commits_per_day(dots, level_commits), timezone)
+}
+
+// the wall clock time in `timezone` with the offset it has on that day; when DST repeats the hour
+// the earlier one is taken, when DST skips it the time moves an hour later
+fn at_local_time(timezone: Tz, date_time: NaiveDateTime) -> DateTime<FixedOffset> {
+  let zoned = timezone
+    .from_local_datetime(&date_time)
+    .earliest()
+    .or_else(|| timezone.from_local_datetime(&(date_time + Duration::hours(1))).earliest())
+    .unwrap_or_else(|| timezone.from_utc_datetime(&date_time));
+  zoned.with_timezone(&zoned.offset().fix())
+}
+
+// `commits` has a number of commits for every day, in columns of weeks like the dots;
+// every commit is made in the morning of its day in `timezone`
+pub fn commits_to_dates(start_date: NaiveDate, commits: &[[u32; 7]], timezone: Tz) -> Vec<DateTime<FixedOffset>> {
   let mut dates = Vec::new();
-  
-  let mut column: usize = 0;
-  while column < dots.len() {
-    for week_day in 0..7 {
-      let i: usize = week_day;
-      if dots[column][i] > 0 {
-        let days: i64 = (i + 7 * column).try_into().unwrap();
-        let duration = Duration::days(days);
-        let date: NaiveDate = start_date + duration;
-        for n in 0..dots[column][i] as u32 {
-          let min: u32 = n / 60;
-          let sec: u32 = n % 60;
-          dates.push(date.and_hms(9, min, sec));
-        }
+  for (column, week) in commits.iter().enumerate() {
+    for (week_day, count) in week.iter().enumerate() {
+      let date = start_date + Duration::days((7 * column + week_day) as i64);
+      for n in 0..(*count).min(MAX_DAY_COMMITS) {
+        dates.push(at_local_time(timezone, date.and_hms(9, 0, 0) + Duration::seconds(n as i64)));
       }
     }
-    column += 1;
   }
   dates
 }
 
+// The calendar colours the cells relative to the busiest day, so the painted level 4 has to
+// reach it and every lower level gets the bottom of its quartile. `minimum` is used when
+// the real activity is lower
+pub fn level_commits_over(busiest_day: u32, minimum: &LevelCommits) -> LevelCommits {
+  if busiest_day <= minimum[3] {
+    return *minimum;
+  }
+  // a busier day than a painted one can hold only gets as close as possible
+  let top = busiest_day.min(MAX_DAY_COMMITS);
+  let mut level_commits = [0; 4];
+  let mut previous = 0;
+  for (level, commits) in level_commits.iter_mut().enumerate() {
+    // rounding up would push the count into the next shade
+    *commits = (top * (level as u32 + 1) / 4).max(previous + 1);
+    previous = *commits;
+  }
+  level_commits
+}
+
+// commits to add on every day, so that together with the real ones the day reaches its level;
+// also returns the days where the real activity alone is more than the pattern wants there
+pub fn commits_over_baseline(start_date: NaiveDate, dots: &[[u8; 7]], level_commits: &LevelCommits, baseline: &Contributions) -> (Vec<[u32; 7]>, Vec<NaiveDate>) {
+  let mut noisy = Vec::new();
+  let mut commits = commits_per_day(dots, level_commits);
+  for (column, week) in commits.iter_mut().enumerate() {
+    for (week_day, count) in week.iter_mut().enumerate() {
+      let date = start_date + Duration::days((7 * column + week_day) as i64);
+      let real = baseline.get(&date).copied().unwrap_or(0);
+      if real > *count {
+        noisy.push(date);
+      }
+      *count = count.saturating_sub(real);
+    }
+  }
+  (commits, noisy)
+}
+
 #[allow(dead_code)]
-fn print_dates(dates: &Vec<[Option<NaiveDateTime>; 7]>) {
+fn print_dates(dates: &[[Option<NaiveDateTime>; 7]]) {
   for week_day in 0..7 {
     let mut column = 0;
     while column < dates.len() {
-      let i = dates[column][week_day];
-      if i.is_some() {
-        print!("{} ", i.expect("date is ok").format("%Y-%m-%d").to_string());
+      if let Some(i) = dates[column][week_day] {
+        print!("{} ", i.format("%Y-%m-%d"));
       } else {
         print!("xxxx-xx-xx ");
       }
       column +=1;
     }
-    println!("");
+    println!();
+  }
+}
+
+pub fn print_dates_flat(dates: &[DateTime<FixedOffset>]) {
+  for date_time in dates {
+    println!("{} {}", date_time.format("%Y-%m-%d %H:%M:%S %:z"), date_time.format("%a"));
   }
+  println!("{} commits in total", dates.len());
 }
 
 
@@ -123,4 +201,44 @@ fn test_borrowing_iterator() {
   ];
   let numbers: Vec<i32> = wrap_into_iter(&dates).copied().collect();
   assert_eq!(numbers, vec![1,2,3,4,5,6,7,8,9,10,11,12,13]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_commits_over_baseline() {
+  // a real day with 30 commits is the one to match, the quiet calendar keeps the minimum
+  assert_eq!(level_commits_over(30, &[1, 2, 3, 4]), [7, 15, 22, 30]);
+  assert_eq!(level_commits_over(2, &[1, 5, 10, 20]), [1, 5, 10, 20]);
+  assert_eq!(level_commits_over(100_000, &[1, 2, 3, 4])[3], MAX_DAY_COMMITS);
+  // every level gets its own shade next to the busiest day
+  for busiest_day in [5, 7, 30, 101, MAX_DAY_COMMITS] {
+    let level_commits = level_commits_over(busiest_day, &[1, 2, 3, 4]);
+    for level in 1..=4 {
+      assert_eq!(color_level(level_commits[level - 1], busiest_day), level, "busiest day {busiest_day}");
+    }
+  }
+
+  let start = NaiveDate::from_ymd(2022, 1, 2);
+  let baseline = Contributions::from([(start, 3), (start + Duration::days(1), 9), (start + Duration::days(2), 1)]);
+  let (commits, noisy) = commits_over_baseline(start, &[[2, 1, 0, 4, 0, 0, 0]], &[4, 8, 12, 16], &baseline);
+  assert_eq!(commits, vec![[5, 0, 0, 16, 0, 0, 0]]);
+  assert_eq!(noisy, vec![start + Duration::days(1), start + Duration::days(2)]);
+  assert_eq!(commits_to_dates(start, &commits, Tz::UTC).len(), 21);
+  // the last commit of the busiest possible day is still on the same day
+  let full = commits_to_dates(start, &[[MAX_DAY_COMMITS, 0, 0, 0, 0, 0, 0]], Tz::UTC);
+  assert_eq!(full.last().unwrap().to_rfc3339(), "2022-01-02T23:59:59+00:00");
+}
+
+#[test]
+fn test_dates_carry_the_offset() {
+  let dates = dots_to_dates_flat(NaiveDate::from_ymd(2022, 3, 6), &[[1, 0, 0, 0, 0, 0, 0], [1, 0, 0, 0, 0, 0, 0]], &[1, 2, 3, 4], Tz::Europe__Berlin);
+  // DST starts in Berlin on March 27, the day stays the same, the offset changes
+  assert_eq!(dates[0].to_rfc3339(), "2022-03-06T09:00:00+01:00");
+  assert_eq!(dates[1].to_rfc3339(), "2022-03-13T09:00:00+01:00");
+  let summer = at_local_time(Tz::Europe__Berlin, NaiveDate::from_ymd(2022, 3, 27).and_hms(9, 0, 0));
+  assert_eq!(summer.to_rfc3339(), "2022-03-27T09:00:00+02:00");
+  // the skipped hour moves forward, the repeated one takes the earlier offset
+  let skipped = at_local_time(Tz::Europe__Berlin, NaiveDate::from_ymd(2022, 3, 27).and_hms(2, 30, 0));
+  assert_eq!(skipped.to_rfc3339(), "2022-03-27T03:30:00+02:00");
+  let repeated = at_local_time(Tz::Europe__Berlin, NaiveDate::from_ymd(2022, 10, 30).and_hms(2, 30, 0));
+  assert_eq!(repeated.to_rfc3339(), "2022-10-30T02:30:00+02:00");
+}
@@ -1,13 +1,84 @@
+use std::fs;
+use std::path::Path;
 
-#[allow(dead_code)]
-fn print_dots(dots: &Vec<[u8; 7]>) {
+use crate::error::{Error, Result};
+
+// Plain text form of the dots, seven lines for Sunday..Saturday, one character per week:
+// '.' or ' ' for an empty day, '1'..'4' for the level, '#' for the maximum level
+
+const MAX_LEVEL: u8 = 4;
+
+pub fn print_dots(dots: &[[u8; 7]]) {
+  print!("{}", format_dots(dots));
+}
+
+pub fn format_dots(dots: &[[u8; 7]]) -> String {
+  let mut text = String::new();
   for week_day in 0..7 {
-    let mut column = 0;
-    while column < dots.len() {
-      let i = dots[column][week_day];
-      print!("{i}");
-      column +=1;
+    text.extend(dots.iter().map(|week| dot_symbol(week[week_day])));
+    text.push('\n');
+  }
+  text
+}
+
+fn dot_symbol(level: u8) -> char {
+  match level {
+    0 => '.',
+    1..=MAX_LEVEL => char::from(b'0' + level),
+    _ => '#',
+  }
+}
+
+fn symbol_level(symbol: char) -> Option<u8> {
+  match symbol {
+    '.' | ' ' => Some(0),
+    '1'..='4' => Some(symbol as u8 - b'0'),
+    '#' => Some(MAX_LEVEL),
+    _ => None,
+  }
+}
+
+pub fn parse_dots(text: &str) -> Result<Vec<[u8; 7]>> {
+  let mut rows: Vec<&str> = text.lines().collect();
+  while rows.len() > 7 && rows.last() == Some(&"") {
+    rows.pop();
+  }
+  if rows.len() != 7 {
+    let message = format!("pattern must have 7 lines, one per week day, but it has {}", rows.len());
+    return Err(Error::Render(message));
+  }
+
+  // editors like to strip trailing spaces, so short lines are padded with empty days
+  let weeks = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+  let mut dots = vec![[0; 7]; weeks];
+  for (week_day, row) in rows.iter().enumerate() {
+    for (column, symbol) in row.chars().enumerate() {
+      dots[column][week_day] = symbol_level(symbol).ok_or_else(|| {
+        Error::Render(format!("unexpected '{symbol}' in line {}, column {}", week_day + 1, column + 1))
+      })?;
     }
-    println!("");
   }
+  Ok(dots)
+}
+
+pub fn load_dots(path: &Path) -> Result<Vec<[u8; 7]>> {
+  parse_dots(&fs::read_to_string(path)?)
+}
+
+#[test]
+fn test_pattern_round_trip() {
+  let text = "\
+.1..#
+.2.
+.3  4
+.4..1
+.....
+.###.
+.....
+";
+  let dots = parse_dots(text).unwrap();
+  assert_eq!(dots.len(), 5);
+  assert_eq!(dots[1], [1, 2, 3, 4, 0, 4, 0]);
+  assert_eq!(dots[4], [4, 0, 4, 1, 0, 0, 0]);
+  assert_eq!(parse_dots(&format_dots(&dots)).unwrap(), dots);
 }
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::io;
+
+// Every failure the brush can run into, from logging in to pushing the last commit
+
+#[derive(Debug)]
+pub enum Error {
+  // no usable access token
+  Auth(AuthError),
+  // the API answered, but not with what was asked for
+  Api {
+    request: String,
+    status: u16,
+    body: String,
+  },
+  // the API could not be reached or its answer could not be read
+  Http(reqwest::Error),
+  Git(String),
+  // the pattern cannot be turned into the activity graph
+  Render(String),
+  // the run was stopped on purpose, nothing was changed
+  Aborted(String),
+  // the push went through, but the calendar does not show what was painted
+  Verify(String),
+  Io(io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+  // the device code was not entered in time
+  Expired,
+  Denied,
+  // any other error from the device flow
+  Failed(String),
+  // the token from the environment is revoked, expired or lacks scopes
+  InvalidToken(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Auth(error) => write!(f, "authorization failed: {error}"),
+      Error::Api { request, status, body } => write!(f, "{request} returned {status}: {body}"),
+      Error::Http(error) => write!(f, "request failed: {error}"),
+      Error::Git(message) => write!(f, "git: {message}"),
+      Error::Render(message) => write!(f, "cannot render the pattern: {message}"),
+      Error::Aborted(message) => write!(f, "stopped: {message}"),
+      Error::Verify(message) => write!(f, "the calendar does not match the painting: {message}"),
+      Error::Io(error) => write!(f, "{error}"),
+    }
+  }
+}
+
+impl fmt::Display for AuthError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AuthError::Expired => write!(f, "the code expired before it was entered, run the command again"),
+      AuthError::Denied => write!(f, "the authorization was denied"),
+      AuthError::Failed(error) => write!(f, "failed obtaining the access token: {error}"),
+      AuthError::InvalidToken(source) => write!(f, "the token from {source} is revoked, expired or lacks scopes"),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Http(error) => Some(error),
+      Error::Io(error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+impl From<AuthError> for Error {
+  fn from(error: AuthError) -> Self {
+    Error::Auth(error)
+  }
+}
+
+impl From<reqwest::Error> for Error {
+  fn from(error: reqwest::Error) -> Self {
+    Error::Http(error)
+  }
+}
+
+impl From<git2::Error> for Error {
+  fn from(error: git2::Error) -> Self {
+    Error::Git(error.message().to_string())
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(error: io::Error) -> Self {
+    Error::Io(error)
+  }
+}
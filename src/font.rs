@@ -0,0 +1,280 @@
+use clap::ValueEnum;
+
+// Built-in bitmap fonts: every glyph pixel is exactly one cell of the activity graph,
+// so the result does not depend on the fonts installed on the machine
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BitmapFont {
+  /// 5 columns by 7 rows, uses the whole height of the graph
+  #[clap(name = "5x7")]
+  Font5x7,
+  /// 3 columns by 5 rows, fits more letters, placed between Monday and Friday
+  #[clap(name = "3x5")]
+  Font3x5,
+}
+
+// level of the lit pixels
+const INK: u8 = 4;
+
+impl BitmapFont {
+  fn width(self) -> usize {
+    match self {
+      BitmapFont::Font5x7 => 5,
+      BitmapFont::Font3x5 => 3,
+    }
+  }
+
+  fn height(self) -> usize {
+    match self {
+      BitmapFont::Font5x7 => 7,
+      BitmapFont::Font3x5 => 5,
+    }
+  }
+
+  // rows of the glyph from top to bottom, the most significant bit is the leftmost column
+  fn glyph(self, letter: char) -> &'static [u8] {
+    let index = match letter {
+      ' '..='~' => letter as usize - ' ' as usize,
+      _ => {
+        println!("'{letter}' is not in the bitmap font, drawing '?' instead");
+        '?' as usize - ' ' as usize
+      },
+    };
+    match self {
+      BitmapFont::Font5x7 => &GLYPHS_5X7[index],
+      BitmapFont::Font3x5 => &GLYPHS_3X5[index],
+    }
+  }
+}
+
+pub fn text_to_dots_bitmap(text: &str, font: BitmapFont, letter_spacing: usize) -> Vec<[u8; 7]> {
+  // center the glyph vertically inside the week
+  let top = (7 - font.height()) / 2;
+  let mut dots = Vec::new();
+  for (i, letter) in text.chars().enumerate() {
+    if i > 0 {
+      dots.extend(std::iter::repeat_n([0; 7], letter_spacing));
+    }
+    let glyph = font.glyph(letter);
+    for column in 0..font.width() {
+      let mut week: [u8; 7] = [0; 7];
+      for (row, bits) in glyph.iter().enumerate() {
+        if bits >> (font.width() - 1 - column) & 1 == 1 {
+          week[top + row] = INK;
+        }
+      }
+      dots.push(week);
+    }
+  }
+  dots
+}
+
+const GLYPHS_5X7: [[u8; 7]; 95] = [
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+  [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // !
+  [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00], // "
+  [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a], // #
+  [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04], // $
+  [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // %
+  [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d], // &
+  [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // '
+  [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // (
+  [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // )
+  [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00], // *
+  [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00], // +
+  [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08], // ,
+  [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00], // -
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c], // .
+  [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // /
+  [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e], // 0
+  [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e], // 1
+  [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f], // 2
+  [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e], // 3
+  [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02], // 4
+  [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e], // 5
+  [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e], // 6
+  [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // 7
+  [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e], // 8
+  [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c], // 9
+  [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00], // :
+  [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08], // ;
+  [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // <
+  [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00], // =
+  [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // >
+  [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+  [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e], // @
+  [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // A
+  [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e], // B
+  [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e], // C
+  [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c], // D
+  [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f], // E
+  [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10], // F
+  [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f], // G
+  [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // H
+  [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // I
+  [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c], // J
+  [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+  [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f], // L
+  [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11], // M
+  [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // N
+  [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // O
+  [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10], // P
+  [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d], // Q
+  [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11], // R
+  [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e], // S
+  [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+  [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // U
+  [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04], // V
+  [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a], // W
+  [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11], // X
+  [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04], // Y
+  [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f], // Z
+  [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e], // [
+  [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // \
+  [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e], // ]
+  [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00], // ^
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f], // _
+  [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // `
+  [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f], // a
+  [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e], // b
+  [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e], // c
+  [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f], // d
+  [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e], // e
+  [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08], // f
+  [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e], // g
+  [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11], // h
+  [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e], // i
+  [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c], // j
+  [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12], // k
+  [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // l
+  [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11], // m
+  [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11], // n
+  [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e], // o
+  [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10], // p
+  [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01], // q
+  [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10], // r
+  [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e], // s
+  [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06], // t
+  [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d], // u
+  [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04], // v
+  [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a], // w
+  [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11], // x
+  [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e], // y
+  [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f], // z
+  [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // {
+  [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // |
+  [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // }
+  [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00], // ~
+];
+
+// lowercase letters reuse the capitals, there is no room for ascenders in five rows
+const GLYPHS_3X5: [[u8; 5]; 95] = [
+  [0x00, 0x00, 0x00, 0x00, 0x00], // space
+  [0x02, 0x02, 0x02, 0x00, 0x02], // !
+  [0x05, 0x05, 0x00, 0x00, 0x00], // "
+  [0x05, 0x07, 0x05, 0x07, 0x05], // #
+  [0x03, 0x04, 0x02, 0x01, 0x06], // $
+  [0x05, 0x01, 0x02, 0x04, 0x05], // %
+  [0x02, 0x05, 0x02, 0x05, 0x03], // &
+  [0x02, 0x02, 0x00, 0x00, 0x00], // '
+  [0x01, 0x02, 0x02, 0x02, 0x01], // (
+  [0x04, 0x02, 0x02, 0x02, 0x04], // )
+  [0x00, 0x05, 0x02, 0x05, 0x00], // *
+  [0x00, 0x02, 0x07, 0x02, 0x00], // +
+  [0x00, 0x00, 0x00, 0x02, 0x04], // ,
+  [0x00, 0x00, 0x07, 0x00, 0x00], // -
+  [0x00, 0x00, 0x00, 0x00, 0x02], // .
+  [0x01, 0x01, 0x02, 0x04, 0x04], // /
+  [0x07, 0x05, 0x05, 0x05, 0x07], // 0
+  [0x02, 0x06, 0x02, 0x02, 0x07], // 1
+  [0x06, 0x01, 0x02, 0x04, 0x07], // 2
+  [0x06, 0x01, 0x02, 0x01, 0x06], // 3
+  [0x05, 0x05, 0x07, 0x01, 0x01], // 4
+  [0x07, 0x04, 0x06, 0x01, 0x06], // 5
+  [0x03, 0x04, 0x07, 0x05, 0x07], // 6
+  [0x07, 0x01, 0x02, 0x02, 0x02], // 7
+  [0x07, 0x05, 0x07, 0x05, 0x07], // 8
+  [0x07, 0x05, 0x07, 0x01, 0x06], // 9
+  [0x00, 0x02, 0x00, 0x02, 0x00], // :
+  [0x00, 0x02, 0x00, 0x02, 0x04], // ;
+  [0x01, 0x02, 0x04, 0x02, 0x01], // <
+  [0x00, 0x07, 0x00, 0x07, 0x00], // =
+  [0x04, 0x02, 0x01, 0x02, 0x04], // >
+  [0x06, 0x01, 0x02, 0x00, 0x02], // ?
+  [0x02, 0x05, 0x07, 0x04, 0x03], // @
+  [0x02, 0x05, 0x07, 0x05, 0x05], // A
+  [0x06, 0x05, 0x06, 0x05, 0x06], // B
+  [0x03, 0x04, 0x04, 0x04, 0x03], // C
+  [0x06, 0x05, 0x05, 0x05, 0x06], // D
+  [0x07, 0x04, 0x07, 0x04, 0x07], // E
+  [0x07, 0x04, 0x07, 0x04, 0x04], // F
+  [0x03, 0x04, 0x05, 0x05, 0x03], // G
+  [0x05, 0x05, 0x07, 0x05, 0x05], // H
+  [0x07, 0x02, 0x02, 0x02, 0x07], // I
+  [0x01, 0x01, 0x01, 0x05, 0x02], // J
+  [0x05, 0x05, 0x06, 0x05, 0x05], // K
+  [0x04, 0x04, 0x04, 0x04, 0x07], // L
+  [0x05, 0x07, 0x07, 0x05, 0x05], // M
+  [0x05, 0x07, 0x07, 0x07, 0x05], // N
+  [0x02, 0x05, 0x05, 0x05, 0x02], // O
+  [0x06, 0x05, 0x06, 0x04, 0x04], // P
+  [0x02, 0x05, 0x05, 0x07, 0x03], // Q
+  [0x06, 0x05, 0x06, 0x05, 0x05], // R
+  [0x03, 0x04, 0x02, 0x01, 0x06], // S
+  [0x07, 0x02, 0x02, 0x02, 0x02], // T
+  [0x05, 0x05, 0x05, 0x05, 0x03], // U
+  [0x05, 0x05, 0x05, 0x02, 0x02], // V
+  [0x05, 0x05, 0x07, 0x07, 0x05], // W
+  [0x05, 0x05, 0x02, 0x05, 0x05], // X
+  [0x05, 0x05, 0x02, 0x02, 0x02], // Y
+  [0x07, 0x01, 0x02, 0x04, 0x07], // Z
+  [0x07, 0x04, 0x04, 0x04, 0x07], // [
+  [0x04, 0x04, 0x02, 0x01, 0x01], // \
+  [0x07, 0x01, 0x01, 0x01, 0x07], // ]
+  [0x02, 0x05, 0x00, 0x00, 0x00], // ^
+  [0x00, 0x00, 0x00, 0x00, 0x07], // _
+  [0x04, 0x02, 0x00, 0x00, 0x00], // `
+  [0x02, 0x05, 0x07, 0x05, 0x05], // a
+  [0x06, 0x05, 0x06, 0x05, 0x06], // b
+  [0x03, 0x04, 0x04, 0x04, 0x03], // c
+  [0x06, 0x05, 0x05, 0x05, 0x06], // d
+  [0x07, 0x04, 0x07, 0x04, 0x07], // e
+  [0x07, 0x04, 0x07, 0x04, 0x04], // f
+  [0x03, 0x04, 0x05, 0x05, 0x03], // g
+  [0x05, 0x05, 0x07, 0x05, 0x05], // h
+  [0x07, 0x02, 0x02, 0x02, 0x07], // i
+  [0x01, 0x01, 0x01, 0x05, 0x02], // j
+  [0x05, 0x05, 0x06, 0x05, 0x05], // k
+  [0x04, 0x04, 0x04, 0x04, 0x07], // l
+  [0x05, 0x07, 0x07, 0x05, 0x05], // m
+  [0x05, 0x07, 0x07, 0x07, 0x05], // n
+  [0x02, 0x05, 0x05, 0x05, 0x02], // o
+  [0x06, 0x05, 0x06, 0x04, 0x04], // p
+  [0x02, 0x05, 0x05, 0x07, 0x03], // q
+  [0x06, 0x05, 0x06, 0x05, 0x05], // r
+  [0x03, 0x04, 0x02, 0x01, 0x06], // s
+  [0x07, 0x02, 0x02, 0x02, 0x02], // t
+  [0x05, 0x05, 0x05, 0x05, 0x03], // u
+  [0x05, 0x05, 0x05, 0x02, 0x02], // v
+  [0x05, 0x05, 0x07, 0x07, 0x05], // w
+  [0x05, 0x05, 0x02, 0x05, 0x05], // x
+  [0x05, 0x05, 0x02, 0x02, 0x02], // y
+  [0x07, 0x01, 0x02, 0x04, 0x07], // z
+  [0x03, 0x02, 0x06, 0x02, 0x03], // {
+  [0x02, 0x02, 0x02, 0x02, 0x02], // |
+  [0x06, 0x02, 0x03, 0x02, 0x06], // }
+  [0x00, 0x03, 0x06, 0x00, 0x00], // ~
+];
+
+#[test]
+fn test_bitmap_letters() {
+  let dots = text_to_dots_bitmap("T1", BitmapFont::Font5x7, 1);
+  assert_eq!(dots.len(), 11);
+  assert_eq!(dots[0], [4, 0, 0, 0, 0, 0, 0]);
+  assert_eq!(dots[2], [4, 4, 4, 4, 4, 4, 4]);
+  assert_eq!(dots[5], [0; 7]);
+  assert_eq!(dots[8], [4, 4, 4, 4, 4, 4, 4]);
+
+  let dots = text_to_dots_bitmap("l", BitmapFont::Font3x5, 1);
+  assert_eq!(dots, vec![[0, 4, 4, 4, 4, 4, 0], [0, 0, 0, 0, 0, 4, 0], [0, 0, 0, 0, 0, 4, 0]]);
+}
@@ -0,0 +1,244 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use reqwest::{Response, StatusCode};
+use std::io;
+
+use crate::error::{AuthError, Error, Result};
+use crate::git::Remote;
+use crate::gitea::Gitea;
+use crate::github::GitHub;
+use crate::gitlab::GitLab;
+use crate::token::{env_token, remove_token, stored_token, store_token, TOKEN_ENV};
+
+// Everything the brush needs from a code hosting service: an account, an empty repository
+// and a URL to push to. Only some calendars are drawn from commit dates, others count the push
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ForgeKind {
+  /// github.com or GitHub Enterprise Server
+  Github,
+  /// gitlab.com or a self-managed GitLab; its calendar counts pushes, so the pattern does not show up
+  Gitlab,
+  /// Gitea or Forgejo, codeberg.org unless --host says otherwise; its heatmap counts pushes too
+  Gitea,
+}
+
+impl ForgeKind {
+  fn default_host(&self) -> &'static str {
+    match self {
+      ForgeKind::Github => "github.com",
+      ForgeKind::Gitlab => "gitlab.com",
+      ForgeKind::Gitea => "codeberg.org",
+    }
+  }
+}
+
+// where the forge lives: a public instance, a self-hosted one or a test server
+#[derive(Clone, Debug)]
+pub struct Host {
+  // stored tokens are kept under this name
+  pub name: String,
+  pub web_url: String,
+}
+
+impl Host {
+  // `host` is a name like "ghe.example.com" or a URL with the scheme, like "http://127.0.0.1:8080"
+  pub fn new(host: &str) -> Self {
+    let host = host.trim_end_matches('/');
+    let web_url = match host.contains("://") {
+      true => host.to_string(),
+      false => format!("https://{host}"),
+    };
+    let name = web_url.split_once("://").map_or(host, |(_, name)| name).to_string();
+    Host { name, web_url }
+  }
+}
+
+// commits per day as the activity calendar of the profile counts them
+pub type Contributions = BTreeMap<NaiveDate, u32>;
+
+// what is shown before the repository is deleted
+pub struct RepoInfo {
+  pub full_name: String,
+  pub stars: u32,
+  pub pushed_at: Option<String>,
+}
+
+#[async_trait]
+pub trait Forge: Sync {
+  fn host(&self) -> &Host;
+  // the painting goes into a private repository
+  fn private(&self) -> bool;
+  // asks the user to log in, when there is no stored token or it stopped working
+  async fn new_token(&self) -> Result<String>;
+  // login of the token owner, or None when the token is revoked or lacks permissions
+  async fn check_token(&self, token: &str) -> Result<Option<String>>;
+  async fn login(&self, token: &str) -> Result<String>;
+  // `requested` is checked against the verified addresses, otherwise the best of them is picked
+  async fn email(&self, token: &str, requested: Option<&str>) -> Result<String>;
+  async fn get_repo(&self, token: &str, owner: &str, repo: &str) -> Result<Option<RepoInfo>>;
+  async fn create_repo(&self, token: &str, owner: &str, repo: &str) -> Result<()>;
+  async fn delete_repo(&self, token: &str, owner: &str, repo: &str) -> Result<()>;
+
+  // whether other people see contributions to private repositories on the profile, None when unknown
+  async fn private_contributions_visible(&self, _token: &str, _login: &str) -> Result<Option<bool>> {
+    Ok(None)
+  }
+
+  // false when the calendar counts the day of the push instead of the commit dates,
+  // then the whole painting lands on a single cell of today
+  fn counts_commit_dates(&self) -> bool {
+    true
+  }
+
+  // the calendar from `from` to `to` inclusive, None when the forge does not tell
+  async fn contributions(&self, _token: &str, _login: &str, _from: NaiveDate, _to: NaiveDate) -> Result<Option<Contributions>> {
+    Ok(None)
+  }
+
+  fn push_url(&self, owner: &str, repo: &str) -> String {
+    format!("{}/{owner}/{repo}.git", self.host().web_url)
+  }
+}
+
+// `client_id` is the OAuth app for the GitHub device flow, the other forges take a pasted token
+pub fn connect(kind: ForgeKind, host: Option<&str>, private: bool, client_id: Option<&str>) -> Box<dyn Forge> {
+  let host = Host::new(host.unwrap_or(kind.default_host()));
+  match kind {
+    ForgeKind::Github => Box::new(GitHub::new(host, private, client_id.map(str::to_string))),
+    ForgeKind::Gitlab => Box::new(GitLab::new(host, private)),
+    ForgeKind::Gitea => Box::new(Gitea::new(host, private)),
+  }
+}
+
+// anything but the `expected` status becomes an error with the explanation from the body
+pub async fn expect_status(response: Response, expected: StatusCode, request: &str) -> Result<Response> {
+  if response.status() == expected {
+    return Ok(response);
+  }
+  let status = response.status().as_u16();
+  let body = response.text().await.unwrap_or_default();
+  Err(Error::Api { request: request.to_string(), status, body })
+}
+
+// for forges without a device flow: the user creates a personal access token and pastes it
+pub fn ask_for_token(url: &str, scopes: &str) -> Result<String> {
+  println!();
+  println!("Create a personal access token with '{scopes}' at {url}");
+  println!("and paste it here:");
+  let mut token = String::new();
+  io::stdin().read_line(&mut token)?;
+  let token = token.trim().to_string();
+  if token.is_empty() {
+    return Err(AuthError::Failed("no token was entered".to_string()).into());
+  }
+  Ok(token)
+}
+
+// the login runs when there is no token for the host yet or the stored one stopped working
+async fn restore_token(forge: &dyn Forge) -> Result<String> {
+  let host = &forge.host().name;
+  if let Some(token) = env_token() {
+    println!("Using the token from {TOKEN_ENV}");
+    return match forge.check_token(&token).await? {
+      Some(_) => Ok(token),
+      None => Err(AuthError::InvalidToken(TOKEN_ENV.to_string()).into()),
+    };
+  }
+
+  if let Some(token) = stored_token(host)? {
+    if forge.check_token(&token).await?.is_some() {
+      println!("Reusing stored token");
+      return Ok(token);
+    }
+    println!("Stored token does not work anymore, authorizing again");
+    remove_token(host, &token)?;
+  }
+
+  println!("Need new token");
+  let token = forge.new_token().await?;
+  let login = forge.login(&token).await?;
+  store_token(host, &login, &token)?;
+  Ok(token)
+}
+
+// who is painting, known after the login
+pub struct Account {
+  pub login: String,
+  pub email: String,
+  token: String,
+}
+
+#[tokio::main]
+// `email` is the author email asked for on the command line
+pub async fn authenticate(forge: &dyn Forge, email: Option<&str>) -> Result<Account> {
+  let token = restore_token(forge).await?;
+  let login = forge.login(&token).await?;
+  let email = forge.email(&token, email).await?;
+  Ok(Account { login, email, token })
+}
+
+#[tokio::main]
+pub async fn fetch_contributions(forge: &dyn Forge, account: &Account, from: NaiveDate, to: NaiveDate) -> Result<Option<Contributions>> {
+  forge.contributions(&account.token, &account.login, from, to).await
+}
+
+#[tokio::main]
+// `keep_existing` reuses the repository when it is already there,
+// `replace` allows to delete it after the user confirms that
+pub async fn prepare_repo(forge: &dyn Forge, account: &Account, repo: &str, keep_existing: bool, replace: bool) -> Result<Remote> {
+  let owner = &account.login;
+  let token = &account.token;
+
+  match forge.get_repo(token, owner, repo).await? {
+    Some(_) if keep_existing => {
+      println!("Repo already exists, the commits will be added to it");
+    },
+    Some(existing) => {
+      if !replace {
+        println!("Pass --replace to delete it and start over, or --append to add the commits to it");
+        return Err(Error::Aborted(format!("repository '{}' already exists", existing.full_name)));
+      }
+      let pushed_at = existing.pushed_at.as_deref().unwrap_or("never");
+      let prompt = format!(
+        "Repository '{}' ({} stars, last push: {pushed_at}) will be deleted with all its history. Continue? (Y/N)",
+        existing.full_name, existing.stars
+      );
+      if !crate::ask_for_confirmation(&prompt)? {
+        return Err(Error::Aborted(format!("repository '{}' was not deleted", existing.full_name)));
+      }
+      println!("Deleting '{}'...", existing.full_name);
+      forge.delete_repo(token, owner, repo).await?;
+      println!("Creating fresh repo '{repo}'");
+      forge.create_repo(token, owner, repo).await?;
+    },
+    None => {
+      println!("Creating fresh repo '{repo}'");
+      forge.create_repo(token, owner, repo).await?;
+    },
+  }
+
+  if forge.private() {
+    match forge.private_contributions_visible(token, owner).await? {
+      Some(true) => println!("The profile shows private contributions, the painting will be visible"),
+      Some(false) => println!("Warning: the profile hides private contributions, nobody but you will see the painting"),
+      None => println!("Warning: could not check that the profile shows private contributions; turn it on in the profile settings, otherwise nobody but you will see the painting"),
+    }
+  }
+
+  Ok(Remote {
+    url: forge.push_url(owner, repo),
+    user: owner.clone(),
+    token: token.clone(),
+  })
+}
+
+#[test]
+fn test_host_names() {
+  let public = Host::new("github.com");
+  assert_eq!((public.name.as_str(), public.web_url.as_str()), ("github.com", "https://github.com"));
+  let local = Host::new("http://127.0.0.1:8080/");
+  assert_eq!((local.name.as_str(), local.web_url.as_str()), ("127.0.0.1:8080", "http://127.0.0.1:8080"));
+}
@@ -1,84 +1,207 @@
-use std::env;
-use std::io::{self, Result, Write, Error, ErrorKind};
+use std::fmt;
+use std::io::{self, Write};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output};
+use clap::ValueEnum;
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GitBackend {
+  /// Write commits in-process with libgit2
+  Libgit2,
+  /// Run the installed `git` binary for every commit
+  Cli,
+}
+
+// name and email of the author or the committer
+#[derive(Clone, Debug)]
+pub struct Identity {
+  pub name: String,
+  pub email: String,
+}
+
+// where to push and who is pushing; the token is kept out of the URL
+// so that it never ends up in .git/config or in the process list
+#[derive(Clone)]
+pub struct Remote {
+  pub url: String,
+  pub user: String,
+  pub token: String,
+}
+
+// and out of logs and panic messages
+impl fmt::Debug for Remote {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Remote")
+      .field("url", &self.url)
+      .field("user", &self.user)
+      .field("token", &"<redacted>")
+      .finish()
+  }
+}
+
+// commit dates as git takes them and as both backends write them into work.txt
+pub const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
 
 // create commits by specifing dates https://stackoverflow.com/questions/454734/how-can-one-change-the-timestamp-of-an-old-commit-in-git
 
-pub fn git_init() -> Result<()> {
-  let output = Command::new("git")
-    .arg("init")
-    .output()?;
+// every command runs inside the repository, the working directory of the process stays the same
+fn git(repo_root: &Path) -> Command {
+  let mut command = Command::new("git");
+  command.current_dir(repo_root);
+  command
+}
+
+// git asks this one-off credential helper, which reads the secret from the environment of the command;
+// the empty helper first drops helpers from the user config so a cached password does not win
+const CREDENTIAL_HELPER: &str = r#"!f() { test "$1" = get && echo "username=$ACTIVITY_BRUSH_GIT_USER" && echo "password=$ACTIVITY_BRUSH_GIT_TOKEN"; }; f"#;
+
+fn with_credentials<'a>(command: &'a mut Command, remote: &Remote) -> &'a mut Command {
+  command
+    .env("GIT_TERMINAL_PROMPT", "0")
+    .env("GIT_CONFIG_COUNT", "2")
+    .env("GIT_CONFIG_KEY_0", "credential.helper")
+    .env("GIT_CONFIG_VALUE_0", "")
+    .env("GIT_CONFIG_KEY_1", "credential.helper")
+    .env("GIT_CONFIG_VALUE_1", CREDENTIAL_HELPER)
+    .env("ACTIVITY_BRUSH_GIT_USER", &remote.user)
+    .env("ACTIVITY_BRUSH_GIT_TOKEN", &remote.token)
+}
+
+fn check_output(output: Output, what: &str) -> Result<()> {
   if !output.status.success() {
-    // println!("{}", output.stderr);
     io::stderr().write_all(&output.stderr)?;
-    return Err(Error::new(ErrorKind::Other, "git init failed"));
+    return Err(Error::Git(format!("{what} failed")));
   }
+  Ok(())
+}
+
+pub fn git_init(repo_root: &Path) -> Result<()> {
+  let output = git(repo_root)
+    .arg("init")
+    .output()?;
+  check_output(output, "git init")?;
   println!("git initialized");
   Ok(())
 }
 
-pub fn git_add(path: &Path) -> Result<()> {
-  let output = Command::new("git")
+pub fn git_add(repo_root: &Path, path: &Path) -> Result<()> {
+  let output = git(repo_root)
     .arg("add")
-    .arg(path.display().to_string())
+    .arg(path)
     .output()?;
-  if !output.status.success() {
-    // println!("{}", output.stderr);
-    io::stderr().write_all(&output.stderr)?;
-    return Err(Error::new(ErrorKind::Other, "git add failed"));
-  }
+  check_output(output, "git add")?;
   println!("file added to tracked files");
   Ok(())
 }
 
-pub fn git_commit(commit_message: &String, date: &String, name: &String, email: &String) -> Result<()> {
-  // GIT_COMMITTER_DATE="2017-10-08T09:51:07" git commit --all --message="commit 1" --date="2017-10-08T09:51:07"
-  env::set_var("GIT_COMMITTER_DATE", format!("{date}"));
-  let output = Command::new("git")
+// only `path` goes into the commit, other changes in an existing repository stay uncommitted
+pub fn git_commit(repo_root: &Path, path: &Path, commit_message: &str, author: &Identity, author_date: &str, committer: &Identity, committer_date: &str) -> Result<()> {
+  // arguments go to git as they are, no shell in between, so nothing needs quoting;
+  // both identities are set for this command only and never come from the local git config
+  let output = git(repo_root)
     .arg("commit")
-    .arg("--all")
-    .arg(format!("--message=\"{commit_message}\""))
-    .arg(format!("--date=\"{date}\""))
-    .arg(format!("--author=\"{name} <{email}>\""))
+    .arg("--message")
+    .arg(commit_message)
+    .env("GIT_AUTHOR_NAME", &author.name)
+    .env("GIT_AUTHOR_EMAIL", &author.email)
+    .env("GIT_AUTHOR_DATE", author_date)
+    .env("GIT_COMMITTER_NAME", &committer.name)
+    .env("GIT_COMMITTER_EMAIL", &committer.email)
+    .env("GIT_COMMITTER_DATE", committer_date)
+    .arg("--")
+    .arg(path)
     .output()?;
-  if !output.status.success() {
-    // println!("{}", output.stderr);
-    io::stderr().write_all(&output.stderr)?;
-    return Err(Error::new(ErrorKind::Other, "git commit failed"));
-  }
-  println!("commited '{commit_message}' ({date})");
+  check_output(output, "git commit")?;
+  println!("commited '{commit_message}' ({author_date})");
+  Ok(())
+}
+
+pub fn git_clone(remote: &Remote, repo_root: &Path) -> Result<()> {
+  let output = with_credentials(&mut Command::new("git"), remote)
+    .arg("clone")
+    .arg(&remote.url)
+    .arg(repo_root)
+    .output()?;
+  check_output(output, "git clone")?;
+  println!("Cloned into {}", repo_root.display());
+  Ok(())
+}
+
+// new branch without history and with an empty index
+pub fn git_switch_orphan(repo_root: &Path, branch: &str) -> Result<()> {
+  let output = git(repo_root)
+    .arg("switch")
+    .arg("--orphan")
+    .arg(branch)
+    .output()?;
+  check_output(output, "git switch")?;
+  println!("switched to orphan branch '{branch}'");
   Ok(())
 }
 
-pub fn git_remote_add(url: &String) -> Result<()> {
-  let output = Command::new("git")
+// adds origin, or points the existing one to `url`
+pub fn git_remote_add(repo_root: &Path, url: &str) -> Result<()> {
+  let output = git(repo_root)
     .arg("remote")
     .arg("add")
     .arg("origin")
     .arg(url)
     .output()?;
   if !output.status.success() {
-    // println!("{}", output.stderr);
-    io::stderr().write_all(&output.stderr)?;
-    return Err(Error::new(ErrorKind::Other, "git remote failed"));
+    let output = git(repo_root)
+      .arg("remote")
+      .arg("set-url")
+      .arg("origin")
+      .arg(url)
+      .output()?;
+    check_output(output, "git remote")?;
   }
   println!("remote url added");
   Ok(())
 }
 
-pub fn git_push() -> Result<()> {
-  let output = Command::new("git")
+// pushes the current branch, whatever its name is; never forced
+pub fn git_push(repo_root: &Path, remote: &Remote) -> Result<()> {
+  let output = with_credentials(&mut git(repo_root), remote)
     .arg("push")
     .arg("-u")
     .arg("origin")
-    .arg("master")
+    .arg("HEAD")
     .output()?;
-  if !output.status.success() {
-    // println!("{}", output.stderr);
-    io::stderr().write_all(&output.stderr)?;
-    return Err(Error::new(ErrorKind::Other, "git push failed"));
-  }
+  check_output(output, "git push")?;
   println!("pushed!");
   Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_commit_metadata() {
+  let repo = tempfile::tempdir().unwrap();
+  let repo_root = repo.path();
+  git_init(repo_root).unwrap();
+  std::fs::write(repo_root.join("work.txt"), "2021-09-05").unwrap();
+  git_add(repo_root, Path::new("work.txt")).unwrap();
+
+  let author = Identity { name: "Jane \"JD\" Doe".to_string(), email: "jane@example.com".to_string() };
+  let committer = Identity { name: "Activity Brush".to_string(), email: "brush@example.com".to_string() };
+  git_commit(repo_root, Path::new("work.txt"), "commit 0", &author, "2021-09-05T09:00:00+00:00", &committer, "2021-09-06T10:30:00+02:00").unwrap();
+
+  let output = git(repo_root)
+    .args(["log", "-1", "--format=%an|%ae|%aI|%cn|%ce|%cI|%B"])
+    .output()
+    .unwrap();
+  let log = String::from_utf8(output.stdout).unwrap();
+  assert_eq!(
+    log.trim_end(),
+    "Jane \"JD\" Doe|jane@example.com|2021-09-05T09:00:00+00:00|Activity Brush|brush@example.com|2021-09-06T10:30:00+02:00|commit 0"
+  );
+}
+
+#[test]
+fn test_remote_debug_hides_token() {
+  let remote = Remote { url: "https://github.com/octocat/activity.git".to_string(), user: "octocat".to_string(), token: "gho_secret".to_string() };
+  let debug = format!("{remote:?}");
+  assert!(debug.contains("octocat/activity.git"));
+  assert!(!debug.contains("gho_secret"));
+}
@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::{Error, Result};
+use crate::forge::{ask_for_token, expect_status, Forge, Host, RepoInfo};
+
+// Gitea and Forgejo share the API under /api/v1
+// https://docs.gitea.com/api/
+
+// token scopes of Gitea 1.19 and later, older versions give full access to every token
+const SCOPE: &str = "write:repository, read:user";
+
+pub struct Gitea {
+  host: Host,
+  api_url: String,
+  private: bool,
+}
+
+impl Gitea {
+  pub fn new(host: Host, private: bool) -> Self {
+    let api_url = format!("{}/api/v1", host.web_url);
+    Gitea { host, api_url, private }
+  }
+
+  fn request(&self, method: Method, path: &str, token: &str) -> RequestBuilder {
+    reqwest::Client::new()
+      .request(method, format!("{}{path}", self.api_url))
+      .header("User-Agent", "Activity Brush by mikolasan")
+      .header("Accept", "application/json")
+      .header("Authorization", format!("token {token}"))
+  }
+}
+
+#[derive(Deserialize, Debug)]
+struct UserResponse {
+  login: String,
+  // skipping the rest
+}
+
+#[derive(Deserialize, Debug)]
+struct EmailResponse {
+  email: String,
+  verified: bool,
+  primary: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepoResponse {
+  full_name: String,
+  stars_count: u32,
+  // Gitea does not tell when the last push was, this is the closest
+  updated_at: Option<String>,
+  // and many other parameters
+}
+
+#[async_trait]
+impl Forge for Gitea {
+  fn host(&self) -> &Host {
+    &self.host
+  }
+
+  fn private(&self) -> bool {
+    self.private
+  }
+
+  // the heatmap counts actions like a push when they happen, not the dates of the commits
+  fn counts_commit_dates(&self) -> bool {
+    false
+  }
+
+  async fn new_token(&self) -> Result<String> {
+    let url = format!("{}/user/settings/applications", self.host.web_url);
+    ask_for_token(&url, SCOPE)
+  }
+
+  // without the scopes the token gets 403 on the first write, there is no way to list them
+  async fn check_token(&self, token: &str) -> Result<Option<String>> {
+    let response = self.request(Method::GET, "/user", token).send().await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+      println!("Token is revoked or expired");
+      return Ok(None);
+    }
+    let response = expect_status(response, StatusCode::OK, "GET /user").await?;
+    Ok(Some(response.json::<UserResponse>().await?.login))
+  }
+
+  async fn login(&self, token: &str) -> Result<String> {
+    let response = self.request(Method::GET, "/user", token).send().await?;
+    let response = expect_status(response, StatusCode::OK, "GET /user").await?;
+    let user = response.json::<UserResponse>().await?;
+    println!("Logged as '{}'", user.login);
+    Ok(user.login)
+  }
+
+  async fn email(&self, token: &str, requested: Option<&str>) -> Result<String> {
+    let response = self.request(Method::GET, "/user/emails", token).send().await?;
+    let response = expect_status(response, StatusCode::OK, "GET /user/emails").await?;
+    let emails = response.json::<Vec<EmailResponse>>().await?;
+    if let Some(requested) = requested {
+      return match emails.iter().any(|e| e.verified && e.email.eq_ignore_ascii_case(requested)) {
+        true => Ok(requested.to_string()),
+        false => Err(Error::Aborted(format!("'{requested}' is not a verified email of the account"))),
+      };
+    }
+    emails
+      .into_iter()
+      .find(|e| e.primary && e.verified)
+      .map(|e| e.email)
+      .ok_or_else(|| Error::Api {
+        request: "GET /user/emails".to_string(),
+        status: StatusCode::OK.as_u16(),
+        body: "no verified primary email in the list".to_string(),
+      })
+  }
+
+  async fn get_repo(&self, token: &str, owner: &str, repo: &str) -> Result<Option<RepoInfo>> {
+    let path = format!("/repos/{owner}/{repo}");
+    let response = self.request(Method::GET, &path, token).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+      return Ok(None);
+    }
+    let response = expect_status(response, StatusCode::OK, &format!("GET {path}")).await?;
+    let repo_response = response.json::<RepoResponse>().await?;
+    Ok(Some(RepoInfo {
+      full_name: repo_response.full_name,
+      stars: repo_response.stars_count,
+      pushed_at: repo_response.updated_at,
+    }))
+  }
+
+  async fn create_repo(&self, token: &str, _owner: &str, repo: &str) -> Result<()> {
+    let input_parameters = json!({
+      "name": repo,
+      "description": "Special repository for displaying nice activity in the profile",
+      "private": self.private,
+      "auto_init": false,
+    });
+    let response = self
+      .request(Method::POST, "/user/repos", token)
+      .json(&input_parameters)
+      .send()
+      .await?;
+    expect_status(response, StatusCode::CREATED, "POST /user/repos").await?;
+    println!("Repo has been created!");
+    Ok(())
+  }
+
+  async fn delete_repo(&self, token: &str, owner: &str, repo: &str) -> Result<()> {
+    let path = format!("/repos/{owner}/{repo}");
+    let response = self.request(Method::DELETE, &path, token).send().await?;
+    expect_status(response, StatusCode::NO_CONTENT, &format!("DELETE {path}")).await?;
+    println!("Repo has been deleted!");
+    Ok(())
+  }
+}
+
+#[tokio::test]
+async fn test_gitea_endpoints() {
+  use wiremock::{Mock, MockServer, ResponseTemplate, matchers};
+  let server = MockServer::start().await;
+  Mock::given(matchers::method("GET"))
+    .and(matchers::path("/api/v1/user"))
+    .and(matchers::header("Authorization", "token secret"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"login": "gitte"})))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("GET"))
+    .and(matchers::path("/api/v1/user/emails"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+      {"email": "old@example.com", "verified": true, "primary": false},
+      {"email": "gitte@example.com", "verified": true, "primary": true},
+    ])))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("POST"))
+    .and(matchers::path("/api/v1/user/repos"))
+    .and(matchers::body_partial_json(json!({"name": "activity", "private": true})))
+    .respond_with(ResponseTemplate::new(201).set_body_json(json!({"full_name": "gitte/activity"})))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("DELETE"))
+    .and(matchers::path("/api/v1/repos/gitte/activity"))
+    .respond_with(ResponseTemplate::new(204))
+    .mount(&server)
+    .await;
+
+  let gitea = Gitea::new(Host::new(&server.uri()), false);
+  assert_eq!(gitea.check_token("secret").await.unwrap().as_deref(), Some("gitte"));
+  assert_eq!(gitea.email("secret", None).await.unwrap(), "gitte@example.com");
+  assert!(gitea.email("secret", Some("other@example.com")).await.is_err());
+  assert!(gitea.get_repo("secret", "gitte", "activity").await.unwrap().is_none());
+  gitea.delete_repo("secret", "gitte", "activity").await.unwrap();
+
+  let private = Gitea::new(Host::new(&server.uri()), true);
+  private.create_repo("secret", "gitte", "activity").await.unwrap();
+  assert_eq!(private.push_url("gitte", "activity"), format!("{}/gitte/activity.git", server.uri()));
+}
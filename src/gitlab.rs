@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::{Error, Result};
+use crate::forge::{ask_for_token, expect_status, Forge, Host, RepoInfo};
+
+// gitlab.com or a self-managed instance; the API is always under /api/v4
+// https://docs.gitlab.com/ee/api/rest/
+
+// personal access tokens need this scope to create and delete projects
+const SCOPE: &str = "api";
+
+pub struct GitLab {
+  host: Host,
+  api_url: String,
+  private: bool,
+}
+
+impl GitLab {
+  pub fn new(host: Host, private: bool) -> Self {
+    let api_url = format!("{}/api/v4", host.web_url);
+    GitLab { host, api_url, private }
+  }
+
+  fn get(&self, path: &str, token: &str) -> RequestBuilder {
+    reqwest::Client::new()
+      .get(format!("{}{path}", self.api_url))
+      .header("User-Agent", "Activity Brush by mikolasan")
+      .header("PRIVATE-TOKEN", token)
+  }
+}
+
+#[derive(Deserialize, Debug)]
+struct UserResponse {
+  username: String,
+  email: Option<String>,
+  commit_email: Option<String>,
+  // hides the whole activity calendar from everybody else
+  #[serde(default)]
+  private_profile: bool,
+  // skipping the rest
+}
+
+#[derive(Deserialize, Debug)]
+struct EmailResponse {
+  email: String,
+  confirmed_at: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+  active: bool,
+  scopes: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProjectResponse {
+  path_with_namespace: String,
+  star_count: u32,
+  last_activity_at: Option<String>,
+  // and many other parameters
+}
+
+// projects are addressed by the URL-encoded full path
+fn project_path(owner: &str, repo: &str) -> String {
+  format!("{owner}%2F{repo}")
+}
+
+async fn get_user(gitlab: &GitLab, token: &str) -> Result<UserResponse> {
+  let response = gitlab.get("/user", token).send().await?;
+  let response = expect_status(response, StatusCode::OK, "GET /user").await?;
+  Ok(response.json::<UserResponse>().await?)
+}
+
+#[async_trait]
+impl Forge for GitLab {
+  fn host(&self) -> &Host {
+    &self.host
+  }
+
+  fn private(&self) -> bool {
+    self.private
+  }
+
+  // GitLab has no device flow for third-party tools
+  async fn new_token(&self) -> Result<String> {
+    let url = format!("{}/-/user_settings/personal_access_tokens?name=Activity+Brush&scopes={SCOPE}", self.host.web_url);
+    ask_for_token(&url, SCOPE)
+  }
+
+  async fn check_token(&self, token: &str) -> Result<Option<String>> {
+    let response = self.get("/personal_access_tokens/self", token).send().await?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+      println!("Token is revoked or expired");
+      return Ok(None);
+    }
+    let response = expect_status(response, StatusCode::OK, "GET /personal_access_tokens/self").await?;
+    let token_info = response.json::<TokenResponse>().await?;
+    if !token_info.active || !token_info.scopes.iter().any(|scope| scope == SCOPE) {
+      println!("Token is inactive or lacks the '{SCOPE}' scope");
+      return Ok(None);
+    }
+    Ok(Some(get_user(self, token).await?.username))
+  }
+
+  async fn login(&self, token: &str) -> Result<String> {
+    let user = get_user(self, token).await?;
+    println!("Logged as '{}'", user.username);
+    Ok(user.username)
+  }
+
+  // the calendar is built from events, a push event is dated when it happens whatever commits it carries
+  fn counts_commit_dates(&self) -> bool {
+    false
+  }
+
+  // the commit email is the one GitLab uses to match commits to the user
+  async fn email(&self, token: &str, requested: Option<&str>) -> Result<String> {
+    let user = get_user(self, token).await?;
+    if let Some(requested) = requested {
+      // the primary address is always confirmed, /user/emails lists only the secondary ones
+      let response = self.get("/user/emails", token).send().await?;
+      let response = expect_status(response, StatusCode::OK, "GET /user/emails").await?;
+      let emails = response.json::<Vec<EmailResponse>>().await?;
+      let confirmed = [&user.email, &user.commit_email].into_iter().flatten().any(|email| email.eq_ignore_ascii_case(requested))
+        || emails.iter().any(|e| e.confirmed_at.is_some() && e.email.eq_ignore_ascii_case(requested));
+      return match confirmed {
+        true => Ok(requested.to_string()),
+        false => Err(Error::Aborted(format!("'{requested}' is not a confirmed email of '{}'", user.username))),
+      };
+    }
+    user.commit_email.or(user.email).ok_or_else(|| Error::Api {
+      request: "GET /user".to_string(),
+      status: StatusCode::OK.as_u16(),
+      body: "the user has no email, pass --author-email".to_string(),
+    })
+  }
+
+  // there is no API for "Include private contributions on my profile", but a private profile hides everything
+  async fn private_contributions_visible(&self, token: &str, _login: &str) -> Result<Option<bool>> {
+    let user = get_user(self, token).await?;
+    Ok(user.private_profile.then_some(false))
+  }
+
+  async fn get_repo(&self, token: &str, owner: &str, repo: &str) -> Result<Option<RepoInfo>> {
+    let path = format!("/projects/{}", project_path(owner, repo));
+    let response = self.get(&path, token).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+      return Ok(None);
+    }
+    let response = expect_status(response, StatusCode::OK, &format!("GET {path}")).await?;
+    let project = response.json::<ProjectResponse>().await?;
+    Ok(Some(RepoInfo {
+      full_name: project.path_with_namespace,
+      stars: project.star_count,
+      pushed_at: project.last_activity_at,
+    }))
+  }
+
+  async fn create_repo(&self, token: &str, _owner: &str, repo: &str) -> Result<()> {
+    let input_parameters = json!({
+      "name": repo,
+      "path": repo,
+      "description": "Special repository for displaying nice activity in the profile",
+      "visibility": if self.private { "private" } else { "public" },
+      "issues_enabled": false,
+      "wiki_enabled": false,
+    });
+    let response = reqwest::Client::new()
+      .post(format!("{}/projects", self.api_url))
+      .header("User-Agent", "Activity Brush by mikolasan")
+      .header("PRIVATE-TOKEN", token)
+      .json(&input_parameters)
+      .send()
+      .await?;
+    expect_status(response, StatusCode::CREATED, "POST /projects").await?;
+    println!("Repo has been created!");
+    Ok(())
+  }
+
+  async fn delete_repo(&self, token: &str, owner: &str, repo: &str) -> Result<()> {
+    let path = format!("/projects/{}", project_path(owner, repo));
+    let url = format!("{}{path}", self.api_url);
+    let response = reqwest::Client::new()
+      .delete(&url)
+      .header("User-Agent", "Activity Brush by mikolasan")
+      .header("PRIVATE-TOKEN", token)
+      .send()
+      .await?;
+    expect_status(response, StatusCode::ACCEPTED, &format!("DELETE {path}")).await?;
+
+    // with delayed deletion the project only gets marked and keeps its path,
+    // so a new project with the same name could not be created
+    let response = reqwest::Client::new()
+      .delete(&url)
+      .query(&[("permanently_remove", "true"), ("full_path", &format!("{owner}/{repo}"))])
+      .header("User-Agent", "Activity Brush by mikolasan")
+      .header("PRIVATE-TOKEN", token)
+      .send()
+      .await?;
+    match response.status() {
+      StatusCode::ACCEPTED | StatusCode::NOT_FOUND => println!("Repo has been deleted!"),
+      status => println!("Repo is marked for deletion, but was not removed right away ({})", status.as_u16()),
+    }
+    Ok(())
+  }
+}
+
+#[tokio::test]
+async fn test_gitlab_endpoints() {
+  use wiremock::{Mock, MockServer, ResponseTemplate, matchers};
+  let server = MockServer::start().await;
+  Mock::given(matchers::method("GET"))
+    .and(matchers::path("/api/v4/user"))
+    .and(matchers::header("PRIVATE-TOKEN", "secret"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"username": "tanuki", "email": "t@example.com", "commit_email": null})))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("GET"))
+    .and(matchers::path("/api/v4/personal_access_tokens/self"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"active": true, "scopes": ["read_user"]})))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("GET"))
+    .and(matchers::path("/api/v4/projects/tanuki%2Factivity"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"path_with_namespace": "tanuki/activity", "star_count": 3, "last_activity_at": null})))
+    .mount(&server)
+    .await;
+  Mock::given(matchers::method("POST"))
+    .and(matchers::path("/api/v4/projects"))
+    .respond_with(ResponseTemplate::new(201).set_body_json(json!({"id": 1})))
+    .mount(&server)
+    .await;
+
+  let gitlab = GitLab::new(Host::new(&server.uri()), false);
+  assert_eq!(gitlab.login("secret").await.unwrap(), "tanuki");
+  assert_eq!(gitlab.email("secret", None).await.unwrap(), "t@example.com");
+  // the token works, but without the api scope projects cannot be created
+  assert!(gitlab.check_token("secret").await.unwrap().is_none());
+  assert_eq!(gitlab.get_repo("secret", "tanuki", "activity").await.unwrap().unwrap().stars, 3);
+  assert!(gitlab.get_repo("secret", "tanuki", "missing").await.unwrap().is_none());
+  gitlab.create_repo("secret", "tanuki", "activity").await.unwrap();
+  assert_eq!(gitlab.push_url("tanuki", "activity"), format!("{}/tanuki/activity.git", server.uri()));
+}
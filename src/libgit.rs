@@ -0,0 +1,181 @@
+use chrono::{DateTime, FixedOffset};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{Cred, FetchOptions, FileMode, PushOptions, RemoteCallbacks, Repository, Signature, Time};
+use std::path::Path;
+
+use crate::git::{Identity, Remote, DATE_FORMAT};
+
+// Same history as the `git` binary produces, but written straight into the object database:
+// no process per commit and no need to change the working directory
+
+type Result<T> = std::result::Result<T, git2::Error>;
+
+const WORK_FILE: &str = "work.txt";
+const DEFAULT_BRANCH: &str = "refs/heads/master";
+
+// the offset is stored in the commit, so the day does not depend on the timezone of this machine
+fn git_time(date_time: &DateTime<FixedOffset>) -> Time {
+  Time::new(date_time.timestamp(), date_time.offset().local_minus_utc() / 60)
+}
+
+// the token is handed to libgit2 on request and never written into the remote URL
+fn remote_credentials(remote: &Remote) -> RemoteCallbacks<'_> {
+  let mut callbacks = RemoteCallbacks::new();
+  callbacks.credentials(|_url, _username_from_url, _allowed_types| {
+    Cred::userpass_plaintext(&remote.user, &remote.token)
+  });
+  callbacks
+}
+
+pub fn init_repository(repo_root: &Path) -> Result<Repository> {
+  let repo = Repository::init(repo_root)?;
+  println!("git initialized");
+  Ok(repo)
+}
+
+// existing history is kept: open the local clone, or clone the remote, or start a new one
+pub fn open_repository(repo_root: &Path, remote: Option<&Remote>) -> Result<Repository> {
+  if repo_root.join(".git").exists() {
+    println!("Opened existing repository in {}", repo_root.display());
+    return Repository::open(repo_root);
+  }
+  match remote {
+    Some(remote) => {
+      let mut fetch_options = FetchOptions::new();
+      fetch_options.remote_callbacks(remote_credentials(remote));
+      let repo = RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(&remote.url, repo_root)?;
+      println!("Cloned into {}", repo_root.display());
+      Ok(repo)
+    },
+    None => init_repository(repo_root),
+  }
+}
+
+// `orphan` starts a new branch without history, otherwise the commits go on top of HEAD;
+// returns the full name of the painted branch
+pub fn paint_commits(repo: &Repository, dates: &[DateTime<FixedOffset>], author: &Identity, committer: &Identity, orphan: Option<&str>) -> Result<String> {
+  let branch = match orphan {
+    Some(name) => {
+      let branch = format!("refs/heads/{name}");
+      if repo.find_reference(&branch).is_ok() {
+        return Err(git2::Error::from_str(&format!("branch '{name}' already exists, pick another name for the orphan branch")));
+      }
+      branch
+    },
+    // HEAD can point to a branch without commits yet, for example right after init or clone
+    None => repo
+      .find_reference("HEAD")?
+      .symbolic_target()
+      .unwrap_or(DEFAULT_BRANCH)
+      .to_string(),
+  };
+
+  let mut parent = match orphan {
+    Some(_) => None,
+    None => repo.find_reference(&branch).ok().map(|reference| reference.peel_to_commit()).transpose()?,
+  };
+  let base_tree = parent.as_ref().map(|commit| commit.tree()).transpose()?;
+
+  // keep whatever work.txt already has and keep appending to it
+  let mut content = String::new();
+  if let Some(entry) = base_tree.as_ref().and_then(|tree| tree.get_name(WORK_FILE)) {
+    let blob = repo.find_blob(entry.id())?;
+    content.push_str(&String::from_utf8_lossy(blob.content()));
+  }
+
+  for (consecutive_counter, date_time) in dates.iter().enumerate() {
+    let date = date_time.format(DATE_FORMAT).to_string();
+    content.push_str(&date);
+
+    let blob = repo.blob(content.as_bytes())?;
+    let mut tree_builder = repo.treebuilder(base_tree.as_ref())?;
+    tree_builder.insert(WORK_FILE, blob, FileMode::Blob.into())?;
+    let tree = repo.find_tree(tree_builder.write()?)?;
+
+    let time = git_time(date_time);
+    let author_signature = Signature::new(&author.name, &author.email, &time)?;
+    let committer_signature = Signature::new(&committer.name, &committer.email, &time)?;
+    let message = format!("commit {consecutive_counter}");
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let commit_id = repo.commit(None, &author_signature, &committer_signature, &message, &tree, &parents)?;
+    parent = Some(repo.find_commit(commit_id)?);
+  }
+
+  if let Some(head) = parent {
+    // safe checkout leaves local changes in other files alone
+    repo.checkout_tree(head.as_object(), Some(CheckoutBuilder::new().safe()))?;
+    repo.reference(&branch, head.id(), true, "activity brush")?;
+    repo.set_head(&branch)?;
+  }
+  println!("{} commits written to {branch}", dates.len());
+  Ok(branch)
+}
+
+pub fn push(repo: &Repository, remote: &Remote, branch: &str) -> Result<()> {
+  let url = remote.url.as_str();
+  let mut origin = match repo.find_remote("origin") {
+    Ok(remote) if remote.url() == Some(url) => remote,
+    Ok(_) => {
+      repo.remote_set_url("origin", url)?;
+      repo.find_remote("origin")?
+    },
+    Err(_) => repo.remote("origin", url)?,
+  };
+  println!("remote url added");
+
+  let mut callbacks = remote_credentials(remote);
+  callbacks.push_update_reference(|reference, status| match status {
+    Some(message) => Err(git2::Error::from_str(&format!("remote rejected {reference}: {message}"))),
+    None => Ok(()),
+  });
+  let mut push_options = PushOptions::new();
+  push_options.remote_callbacks(callbacks);
+
+  // no '+' in the refspec: the push never rewrites history that is already on the remote
+  origin.push(&[format!("{branch}:{branch}")], Some(&mut push_options))?;
+  println!("pushed!");
+  Ok(())
+}
+
+#[cfg(test)]
+fn git_output(repo_root: &Path, args: &[&str]) -> String {
+  let output = std::process::Command::new("git").current_dir(repo_root).args(args).output().unwrap();
+  String::from_utf8(output.stdout).unwrap().trim_end().to_string()
+}
+
+#[test]
+fn test_paint_commits() {
+  use chrono::TimeZone;
+  let dir = tempfile::tempdir().unwrap();
+  let repo_root = dir.path();
+  let offset = FixedOffset::east(2 * 3600);
+  let first = offset.ymd(2021, 9, 5).and_hms(9, 0, 0);
+  let second = offset.ymd(2021, 9, 6).and_hms(9, 0, 1);
+  let author = Identity { name: "Jane \"JD\" Doe".to_string(), email: "jane@example.com".to_string() };
+  let committer = Identity { name: "Activity Brush".to_string(), email: "brush@example.com".to_string() };
+  let repo = init_repository(repo_root).unwrap();
+  let branch = paint_commits(&repo, &[first], &author, &committer, None).unwrap();
+
+  // the offset of the plan is stored, whatever the timezone of this machine is
+  assert_eq!(
+    git_output(repo_root, &["log", "-1", "--format=%an|%ae|%aI|%cn|%ce|%cI"]),
+    "Jane \"JD\" Doe|jane@example.com|2021-09-05T09:00:00+02:00|Activity Brush|brush@example.com|2021-09-05T09:00:00+02:00"
+  );
+
+  // appending goes on top of the existing commit and keeps work.txt
+  let repo = open_repository(repo_root, None).unwrap();
+  assert_eq!(paint_commits(&repo, &[second], &author, &committer, None).unwrap(), branch);
+  let first_id = git_output(repo_root, &["rev-parse", &format!("{branch}~1")]);
+  assert_eq!(git_output(repo_root, &["log", "-1", "--format=%P", &branch]), first_id);
+  assert_eq!(git_output(repo_root, &["rev-list", "--count", &branch]), "2");
+  let work = std::fs::read_to_string(repo_root.join(WORK_FILE)).unwrap();
+  assert_eq!(work, "2021-09-05T09:00:00+02:002021-09-06T09:00:01+02:00");
+
+  // the orphan branch starts without a parent and leaves the painted branch alone
+  assert_eq!(paint_commits(&repo, &[second], &author, &committer, Some("art")).unwrap(), "refs/heads/art");
+  assert_eq!(git_output(repo_root, &["log", "--format=%P", "refs/heads/art"]), "");
+  assert_eq!(git_output(repo_root, &["rev-list", "--count", &branch]), "2");
+  assert!(paint_commits(&repo, &[second], &author, &committer, Some("art")).is_err());
+}
@@ -1,23 +1,45 @@
-use std::fs::{self, File};
-use std::io::{self, Result, Write, ErrorKind};
-use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write, ErrorKind};
 use std::path::Path;
-use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use std::process;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc};
+use clap::{CommandFactory, ErrorKind as ClapErrorKind, Parser};
 
+mod calendar;
+use calendar::CalendarWindow;
+mod cli;
+use cli::{AuthorArgs, Cli, Command, LocalArgs, PatternArgs};
 mod dates;
-use dates::{wrap_into_iter, dots_to_dates, dots_to_dates_flat};
+use dates::{commits_over_baseline, commits_per_day, commits_to_dates, dots_to_dates_flat, level_commits_over, print_dates_flat};
 mod dots;
+mod error;
+use error::{Error, Result};
+use dots::{format_dots, load_dots, print_dots};
+mod font;
+use font::text_to_dots_bitmap;
 mod git;
-use git::{git_init, git_add, git_commit};
+use git::{git_init, git_add, git_commit, GitBackend, Identity, Remote, DATE_FORMAT};
+mod forge;
+use forge::{authenticate, connect, fetch_contributions, prepare_repo, Account, Contributions, Forge};
 mod github;
-use github::{prepare_github};
+mod gitea;
+mod gitlab;
+mod libgit;
+use libgit::{init_repository, open_repository, paint_commits, push};
+mod pipeline;
+use pipeline::Pipeline;
 mod raster;
-use raster::text_to_dots;
+use raster::{image_to_dots, text_to_dots};
+mod svg;
+use svg::dots_to_svg;
+mod token;
+mod verify;
+use verify::{planned_contributions, verify_painting};
 
-use crate::git::{git_remote_add, git_push};
+use crate::git::{git_clone, git_remote_add, git_push, git_switch_orphan};
 
 
-fn directory_exists(path: &Path) -> Result<bool> {
+fn directory_exists(path: &Path) -> io::Result<bool> {
   let result = fs::metadata(path).map(|metadata| metadata.is_dir());
   if let Err(error) = result {
     match error.kind() {
@@ -34,28 +56,29 @@ fn directory_exists(path: &Path) -> Result<bool> {
   result
 }
 
-fn ask_for_confirmation(prompt: &String) -> Result<bool> {
-  let mut input = String::new();
+fn ask_for_confirmation(prompt: &String) -> io::Result<bool> {
   println!("{prompt}");
-  let confirmation = io::stdin()
-    .read_line(&mut input)
-    .map(|_| input.trim_end())?;
-
-  let answer = match confirmation {
-    "Y" => true,
-    "N" => false,
-    _ => {
-      println!("You must print 'Y' or 'N' only!\nI'll ask again...");
-      return ask_for_confirmation(prompt);
+  loop {
+    let mut input = String::new();
+    // a closed stdin can't confirm anything
+    if io::stdin().read_line(&mut input)? == 0 {
+      return Ok(false);
     }
-  };
-  Ok(answer)
+    match input.trim_end() {
+      "Y" => return Ok(true),
+      "N" => return Ok(false),
+      _ => println!("You must print 'Y' or 'N' only!\nI'll ask again...\n{prompt}"),
+    }
+  }
 }
 
-fn dates_to_commits<'a>(date_iterator: impl Iterator<Item = &'a NaiveDateTime>, name: &String, email: &String, git_url: &String) -> Result<()> {
-  // make temp dir
-  let git_repo_dir = "temp_git";
-  let repo_root = Path::new(git_repo_dir);
+// writes the commits and returns the branch that has them; `remote` is cloned when `local` keeps history
+fn dates_to_commits(dates: &[DateTime<FixedOffset>], local: &LocalArgs, author: &Identity, committer: &Identity, remote: Option<&Remote>) -> Result<String> {
+  let repo_root = Path::new(&local.dir);
+  if local.keeps_history() {
+    return append_commits(dates, local, author, committer, remote);
+  }
+
   if directory_exists(repo_root)? {
     let prompt = format!("Do you want do delete '{}' and all its content? (Y/N)", repo_root.display());
     if ask_for_confirmation(&prompt)? {
@@ -69,70 +92,276 @@ fn dates_to_commits<'a>(date_iterator: impl Iterator<Item = &'a NaiveDateTime>,
   }
   
   fs::create_dir(repo_root)?;
-  let return_path = env::current_dir()?;
-  env::set_current_dir(&repo_root)?;
-  println!("Changed working directory to {}", repo_root.display());
-  
-  git_init()?;
-  
+
+  match local.git_backend {
+    GitBackend::Libgit2 => {
+      let repo = init_repository(repo_root)?;
+      Ok(paint_commits(&repo, dates, author, committer, None)?)
+    },
+    GitBackend::Cli => {
+      git_init(repo_root)?;
+      dates_to_commits_cli(dates, repo_root, author, committer)
+    },
+  }
+}
+
+// nothing is deleted: the commits go on top of what the repository already has
+fn append_commits(dates: &[DateTime<FixedOffset>], local: &LocalArgs, author: &Identity, committer: &Identity, remote: Option<&Remote>) -> Result<String> {
+  let repo_root = Path::new(&local.dir);
+  match local.git_backend {
+    GitBackend::Libgit2 => {
+      let repo = open_repository(repo_root, remote)?;
+      Ok(paint_commits(&repo, dates, author, committer, local.orphan.as_deref())?)
+    },
+    GitBackend::Cli => {
+      if directory_exists(&repo_root.join(".git"))? {
+        println!("Opened existing repository in {}", repo_root.display());
+      } else if let Some(remote) = remote {
+        git_clone(remote, repo_root)?;
+      } else {
+        fs::create_dir_all(repo_root)?;
+        git_init(repo_root)?;
+      }
+      if let Some(orphan) = &local.orphan {
+        git_switch_orphan(repo_root, orphan)?;
+      }
+      dates_to_commits_cli(dates, repo_root, author, committer)
+    },
+  }
+}
+
+fn dates_to_commits_cli(dates: &[DateTime<FixedOffset>], repo_root: &Path, author: &Identity, committer: &Identity) -> Result<String> {
   let work_file = "work.txt";
   let file_path = Path::new(work_file);
 
-  let mut file = File::create(file_path)?;
+  // existing content stays, new dates are appended
+  let mut file = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(repo_root.join(file_path))?;
   
   // initial commit
-  git_add(file_path)?;
+  git_add(repo_root, file_path)?;
   
-  let mut consecutive_counter: usize = 0;
-  for date_time in date_iterator {
-    let date = date_time.format("%Y-%m-%dT%H:%M:%S").to_string();
+  for (consecutive_counter, date_time) in dates.iter().enumerate() {
+    let date = date_time.format(DATE_FORMAT).to_string();
 
     file.write_all(date.as_bytes())?;
     git_commit(
+      repo_root,
+      file_path,
       &format!("commit {consecutive_counter}"),
+      author,
       &date,
-      &name,
-      &email
+      committer,
+      &date
     )?;
-    
-    consecutive_counter += 1;
   }
 
-  git_remote_add(&git_url)?;
-  git_push()?;
+  // `git push` takes the current branch, whatever its name is
+  Ok("HEAD".to_string())
+}
 
-  env::set_current_dir(&return_path)?;
-  
+fn push_commits(local: &LocalArgs, remote: &Remote, branch: &str) -> Result<()> {
+  let repo_root = Path::new(&local.dir);
+  match local.git_backend {
+    GitBackend::Libgit2 => {
+      let repo = open_repository(repo_root, None)?;
+      push(&repo, remote, branch)?;
+    },
+    GitBackend::Cli => {
+      git_remote_add(repo_root, &remote.url)?;
+      git_push(repo_root, remote)?;
+    },
+  }
   Ok(())
 }
 
+fn pattern_to_dots(pattern: &PatternArgs) -> Result<Vec<[u8; 7]>> {
+  match (&pattern.text, &pattern.image, &pattern.pattern) {
+    (_, _, Some(pattern_file)) => load_dots(Path::new(pattern_file)),
+    (_, Some(image), None) => image_to_dots(Path::new(image), pattern.image_mode),
+    (Some(text), None, None) => match pattern.bitmap_font {
+      Some(font) => Ok(text_to_dots_bitmap(text, font, pattern.letter_spacing)),
+      None => Ok(text_to_dots(text, &pattern.font)),
+    },
+    (None, None, None) => unreachable!("clap requires --text, --image or --pattern"),
+  }
+}
+
+fn calendar_window(pattern: &PatternArgs) -> Option<CalendarWindow> {
+  if pattern.start_date.is_some() {
+    return None;
+  }
+  let today = today(pattern);
+  match pattern.year {
+    Some(year) => CalendarWindow::year(year, today),
+    None => Some(CalendarWindow::rolling(today)),
+  }
+}
 
-fn main() {
-  let repo = "activity-repo".to_string();
-  let mut email = String::new();
-  let mut git_url = String::new();
-  match prepare_github(repo.to_owned()) {
-    Ok((_email, _git_url)) => {
-      email = _email;
-      git_url = _git_url;
+// the calendar is drawn in the timezone of the commits
+fn today(pattern: &PatternArgs) -> NaiveDate {
+  Utc::now().with_timezone(&pattern.timezone).date().naive_local()
+}
+
+// dots and the Sunday of their first column
+fn place_pattern(pattern: &PatternArgs) -> Result<(NaiveDate, Vec<[u8; 7]>)> {
+  let dots = pattern_to_dots(pattern)?;
+  let placement = match (pattern.start_date, calendar_window(pattern)) {
+    (Some(start_date), _) => (start_date, dots),
+    (None, Some(window)) => window.fit(&dots, pattern.align)?,
+    (None, None) => return Err(Error::Render("no such year in the calendar".to_string())),
+  };
+  println!("start date: {}", placement.0.format("%Y-%m-%d"));
+  Ok(placement)
+}
+
+fn render_pattern(pattern: &PatternArgs) -> Result<Vec<DateTime<FixedOffset>>> {
+  let (start_date, dots) = place_pattern(pattern)?;
+  Ok(dots_to_dates_flat(start_date, &dots, &pattern.level_commits(), pattern.timezone))
+}
+
+// everything that happens before the first git or GitHub call
+fn dry_run(pattern: &PatternArgs, svg_path: Option<&str>, pattern_path: Option<&str>) -> Result<()> {
+  let (start_date, dots) = place_pattern(pattern)?;
+  print_dots(&dots);
+  let dates = dots_to_dates_flat(start_date, &dots, &pattern.level_commits(), pattern.timezone);
+  print_dates_flat(&dates);
+
+  if let Some(pattern_path) = pattern_path {
+    fs::write(pattern_path, format_dots(&dots))?;
+    println!("Pattern saved to {pattern_path}");
+  }
+
+  if let Some(svg_path) = svg_path {
+    // show the whole calendar when the pattern was fitted into one
+    let (start_date, dots) = match calendar_window(pattern) {
+      Some(window) => (window.first_sunday(), window.spread(start_date, &dots)),
+      None => (start_date, dots),
+    };
+    let commits = commits_per_day(&dots, &pattern.level_commits());
+    let svg = dots_to_svg(&commits, start_date);
+    fs::write(svg_path, svg)?;
+    println!("Calendar saved to {svg_path}");
+  }
+  Ok(())
+}
+
+// tells where the run stopped; a script calling the brush sees the failure in the exit code
+fn exit_with_error(step: &str, error: Error) -> ! {
+  eprintln!("Error happened in '{step}': {error}");
+  process::exit(1);
+}
+
+// commit dates, the commits expected per day and the calendar before painting.
+// Real activity sets how many commits each level needs and is topped up on the painted days
+fn plan_push(pattern: &PatternArgs, forge: &dyn Forge, account: &Account) -> Result<(Vec<DateTime<FixedOffset>>, Contributions, Option<Contributions>)> {
+  let (start_date, dots) = place_pattern(pattern)?;
+  // the colours are relative to the calendar the pattern is shown in
+  let window = calendar_window(pattern).or_else(|| CalendarWindow::year(start_date.year(), today(pattern)));
+  // and the verification also looks at the day before and after the pattern
+  let from = start_date - Duration::days(1);
+  let to = start_date + Duration::weeks(dots.len() as i64);
+  let baseline = match window {
+    Some(window) => fetch_contributions(forge, account, from.min(window.first_day), to.max(window.last_day))?
+      .map(|calendar| (window, calendar)),
+    None => None,
+  };
+  let (window, baseline) = match baseline {
+    Some(baseline) => baseline,
+    None => {
+      let commits = commits_per_day(&dots, &pattern.level_commits());
+      return Ok((commits_to_dates(start_date, &commits, pattern.timezone), planned_contributions(start_date, &commits), None));
     },
-    Err(e) => println!("Error happened in 'prepare_github': {e}"),
+  };
+
+  // explicit --level-commits are kept as they are
+  let level_commits = match pattern.level_commits {
+    Some(level_commits) => level_commits,
+    None => {
+      let busiest_day = baseline.range(window.first_day..=window.last_day).map(|(_, count)| *count).max().unwrap_or(0);
+      let level_commits = level_commits_over(busiest_day, &pattern.level_commits());
+      println!("The busiest real day has {busiest_day} commits, levels 1-4 get {level_commits:?} commits");
+      level_commits
+    },
+  };
+  let (commits, noisy) = commits_over_baseline(start_date, &dots, &level_commits, &baseline);
+  if !noisy.is_empty() {
+    println!("Warning: real activity on {} days is already more than the pattern wants there:", noisy.len());
+    for date in noisy {
+      println!("  {date}: {} commits", baseline[&date]);
+    }
   }
-  let text: String = "BOOBS".to_string();
-  let dots = text_to_dots(text);
-  // // print_dots(&dots);
-
-  let start_date = NaiveDate::from_weekday_of_month(2021, 09, Weekday::Sun, 1);
-  println!("start date: {}", start_date.format("%Y-%m-%d").to_string());
-  // let dates = dots_to_dates(start_date, dots);
-  // let it = wrap_into_iter(&dates);
-  
-  let dates = dots_to_dates_flat(start_date, dots);
-  let it = dates.iter();
+  Ok((commits_to_dates(start_date, &commits, pattern.timezone), planned_contributions(start_date, &commits), Some(baseline)))
+}
 
-  let name = "Activity Brush".to_string();
-  match dates_to_commits(it, &name, &email, &git_url) {
-    Ok(_) => {},
-    Err(e) => println!("Error happened in 'dates_to_commits': {e}"),
+// nothing is created on GitHub before the pattern is known to fit
+#[allow(clippy::too_many_arguments)]
+fn push_stages(pipeline: &mut Pipeline, pattern: &PatternArgs, author_args: &AuthorArgs, local: &LocalArgs, forge: &dyn Forge, repo: &str, replace: bool, verify: bool) -> Option<()> {
+  let account = pipeline.stage("authenticate", || authenticate(forge, author_args.author_email.as_deref()))?;
+  let (dates, planned, baseline) = pipeline.stage("plan", || plan_push(pattern, forge, &account))?;
+  let remote = pipeline.stage("create repo", || prepare_repo(forge, &account, repo, local.keeps_history(), replace))?;
+  let (author, committer) = author_args.identities(account.email.clone());
+  let branch = pipeline.stage("paint", || dates_to_commits(&dates, local, &author, &committer, Some(&remote)))?;
+  pipeline.stage("push", || push_commits(local, &remote, &branch))?;
+  // commits of other branches do not show up until the branch becomes the default one
+  let reason = match (verify, local.orphan.is_some(), baseline) {
+    (false, _, _) => "--no-verify",
+    (_, true, _) => "commits of the orphan branch are not counted",
+    (_, _, Some(baseline)) => return pipeline.stage("verify", || verify_painting(forge, &account, &planned, &baseline)),
+    (_, _, None) => "the forge has no contribution calendar",
+  };
+  pipeline.skip("verify", reason);
+  Some(())
+}
+
+fn main() {
+  let cli = Cli::parse();
+  match cli.command {
+    Command::Preview { pattern, svg, save_pattern } => {
+      dry_run(&pattern, svg.as_deref(), save_pattern.as_deref())
+        .unwrap_or_else(|e| exit_with_error("dry_run", e));
+    },
+    Command::Paint { pattern, dry_run: true, .. }
+      | Command::Push { pattern, dry_run: true, .. } => {
+      dry_run(&pattern, None, None).unwrap_or_else(|e| exit_with_error("dry_run", e));
+    },
+    Command::Paint { pattern, author: author_args, local, .. } => {
+      let email = author_args.author_email.clone().unwrap_or_else(|| {
+        Cli::command()
+          .error(ClapErrorKind::MissingRequiredArgument, "'paint' needs --author-email because it does not ask GitHub")
+          .exit()
+      });
+      let (author, committer) = author_args.identities(email);
+      let mut pipeline = Pipeline::new(&["plan", "paint"]);
+      if let Some(dates) = pipeline.stage("plan", || render_pattern(&pattern)) {
+        pipeline.stage("paint", || dates_to_commits(&dates, &local, &author, &committer, None));
+      }
+      if !pipeline.summary() {
+        process::exit(1);
+      }
+    },
+    Command::Push { pattern, author: author_args, local, repo, replace, no_verify, forge, .. } => {
+      let forge = connect(forge.forge, forge.host.as_deref(), forge.private, forge.client_id.as_deref());
+      if !forge.counts_commit_dates() {
+        println!("Warning: the calendar of {} counts the day of the push, not the commit dates;", forge.host().name);
+        println!("the commits will be pushed, but the pattern will not show up on the profile");
+      }
+      if let Some(orphan) = &local.orphan {
+        println!("Warning: the calendar counts only commits of the default branch, the painting shows up after");
+        println!("'{orphan}' is made the default branch of '{repo}' in the repository settings; verify is skipped");
+      }
+      let mut pipeline = Pipeline::new(&["authenticate", "plan", "create repo", "paint", "push", "verify"]);
+      push_stages(&mut pipeline, &pattern, &author_args, &local, forge.as_ref(), &repo, replace, !no_verify);
+      if !pipeline.summary() {
+        process::exit(1);
+      }
+    },
+    Command::Auth { forge } => {
+      let forge = connect(forge.forge, forge.host.as_deref(), forge.private, forge.client_id.as_deref());
+      let account = authenticate(forge.as_ref(), None).unwrap_or_else(|e| exit_with_error("authenticate", e));
+      println!("Authenticated as '{}'", account.login);
+    },
   }
 }
@@ -0,0 +1,87 @@
+use crate::error::{Error, Result};
+
+// Stages of a run in the order they happen; the first failure stops the rest
+
+enum Outcome {
+  Done,
+  Failed(Error),
+  // not run, with the reason when the run decided so
+  Skipped(Option<String>),
+}
+
+pub struct Pipeline {
+  stages: Vec<(&'static str, Outcome)>,
+}
+
+impl Pipeline {
+  pub fn new(names: &[&'static str]) -> Self {
+    Pipeline {
+      stages: names.iter().map(|name| (*name, Outcome::Skipped(None))).collect(),
+    }
+  }
+
+  fn failed(&self) -> bool {
+    self.stages.iter().any(|(_, outcome)| matches!(outcome, Outcome::Failed(_)))
+  }
+
+  // runs the stage unless an earlier one failed; None means that the following stages must not run
+  pub fn stage<T>(&mut self, name: &'static str, run: impl FnOnce() -> Result<T>) -> Option<T> {
+    if self.failed() {
+      return None;
+    }
+    println!("== {name}");
+    let (value, outcome) = match run() {
+      Ok(value) => (Some(value), Outcome::Done),
+      Err(error) => (None, Outcome::Failed(error)),
+    };
+    self.record(name, outcome);
+    value
+  }
+
+  // the stage does not apply to this run, the summary tells why
+  pub fn skip(&mut self, name: &'static str, reason: &str) {
+    println!("== {name} skipped: {reason}");
+    self.record(name, Outcome::Skipped(Some(reason.to_string())));
+  }
+
+  fn record(&mut self, name: &'static str, outcome: Outcome) {
+    if let Some(stage) = self.stages.iter_mut().find(|(stage_name, _)| *stage_name == name) {
+      stage.1 = outcome;
+    }
+  }
+
+  // prints every stage with its outcome, returns false when one of them failed
+  pub fn summary(&self) -> bool {
+    println!();
+    println!("Summary:");
+    for (name, outcome) in &self.stages {
+      match outcome {
+        Outcome::Done => println!("  {name:<12} done"),
+        Outcome::Failed(error) => println!("  {name:<12} FAILED: {error}"),
+        Outcome::Skipped(None) => println!("  {name:<12} skipped"),
+        Outcome::Skipped(Some(reason)) => println!("  {name:<12} skipped: {reason}"),
+      }
+    }
+    !self.failed()
+  }
+}
+
+#[test]
+fn test_failure_stops_later_stages() {
+  let mut pipeline = Pipeline::new(&["first", "second", "third"]);
+  assert_eq!(pipeline.stage("first", || Ok(1)), Some(1));
+  assert_eq!(pipeline.stage("second", || -> Result<i32> { Err(Error::Aborted("test".to_string())) }), None);
+  let mut ran = false;
+  assert_eq!(pipeline.stage("third", || { ran = true; Ok(3) }), None);
+  assert!(!ran);
+  assert!(!pipeline.summary());
+}
+
+#[test]
+fn test_skipped_stage_is_not_a_failure() {
+  let mut pipeline = Pipeline::new(&["first", "second"]);
+  assert_eq!(pipeline.stage("first", || Ok(1)), Some(1));
+  pipeline.skip("second", "nothing to check");
+  assert!(matches!(&pipeline.stages[1].1, Outcome::Skipped(Some(reason)) if reason == "nothing to check"));
+  assert!(pipeline.summary());
+}
@@ -2,6 +2,7 @@ use cairo::{Format,
   FontExtents, 
   TextExtents, 
   Context,
+  Filter,
   ffi::{cairo_move_to, 
     cairo_show_text, 
     cairo_image_surface_create, 
@@ -15,64 +16,126 @@ use cairo::{Format,
   },
   ImageSurface, 
 };
+use clap::ValueEnum;
+use std::ffi::CString;
 use std::fs::File;
+use std::path::Path;
 
-pub fn text_to_dots(text: String) -> Vec<[u8; 7]> {
+use crate::error::{Error, Result};
+
+// which part of a pixel says that the day is busy
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ImageMode {
+  /// Opaque pixels are painted, transparent ones stay empty
+  Alpha,
+  /// Bright pixels are painted, black ones stay empty
+  Luminance,
+  /// Dark pixels are painted, white ones stay empty
+  Inverted,
+}
+
+pub fn text_to_dots(text: &str, font: &str) -> Vec<[u8; 7]> {
   // use font to render a text -> picture
-  let mut surface = text_to_surface(text);
+  let mut surface = text_to_surface(text, font);
+  save_surface_as_png(&surface);
+  // text is drawn in black, so only alpha tells how much of the pixel is covered
+  surface_to_dots(&mut surface, ImageMode::Alpha)
+}
+
+// side of the box that becomes one cell of the activity graph
+const IMAGE_BOX_SIZE: i32 = 8;
+
+pub fn image_to_dots(path: &Path, mode: ImageMode) -> Result<Vec<[u8; 7]>> {
+  let mut file = File::open(path)?;
+  let image = ImageSurface::create_from_png(&mut file)
+    .map_err(|e| Error::Render(format!("cannot read '{}': {e}", path.display())))?;
+  picture_to_dots(&image, mode)
+}
+
+fn picture_to_dots(image: &ImageSurface, mode: ImageMode) -> Result<Vec<[u8; 7]>> {
+  // scale the picture so that its height fits exactly seven boxes
+  let scale = (7 * IMAGE_BOX_SIZE) as f64 / image.height() as f64;
+  let width = (image.width() as f64 * scale).round().max(1.0) as i32;
+  let mut surface = ImageSurface::create(Format::ARgb32, width, 7 * IMAGE_BOX_SIZE)
+    .map_err(|e| Error::Render(e.to_string()))?;
+  {
+    let context = Context::new(&surface)
+      .map_err(|e| Error::Render(e.to_string()))?;
+    context.scale(scale, scale);
+    context.set_source_surface(image, 0.0, 0.0)
+      .map_err(|e| Error::Render(e.to_string()))?;
+    // blending neighbours would smear a pixel-art picture into the next boxes
+    context.source().set_filter(Filter::Nearest);
+    context.paint()
+      .map_err(|e| Error::Render(e.to_string()))?;
+  }
+
+  Ok(surface_to_dots(&mut surface, mode))
+}
+
+// how much of the pixel is "ink", from 0.0 to 1.0
+fn pixel_coverage(pixel: u32, mode: ImageMode) -> f64 {
+  // cairo keeps ARGB32 premultiplied, as if the picture was drawn over black
+  let a = (pixel >> 24 & 0xff) as f64;
+  let r = (pixel >> 16 & 0xff) as f64;
+  let g = (pixel >> 8 & 0xff) as f64;
+  let b = (pixel & 0xff) as f64;
+  let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+  let coverage = match mode {
+    ImageMode::Alpha => a,
+    ImageMode::Luminance => luminance,
+    // same picture drawn over white
+    ImageMode::Inverted => a - luminance,
+  };
+  coverage.max(0.0) / 255.0
+}
+
+fn surface_to_dots(surface: &mut ImageSurface, mode: ImageMode) -> Vec<[u8; 7]> {
   let width: usize = surface.width() as usize;
   let height: usize = surface.height() as usize;
-  save_surface_as_png(&surface);
+  let stride: usize = surface.stride() as usize;
   // println!("reference counter z {}", unsafe {cairo_surface_get_reference_count(surface.to_raw_none())});
   let data = surface.data().unwrap_or_else(|error| {
-    panic!("no data in the surface: {}", error.to_string());
+    panic!("no data in the surface: {}", error);
   });
-  // println!("image data length {}", data.len());
-  // for y in 0..height {
-  //     for x in 0..width {
-  //       // print!("{}", x * 4 + y * width * 4);
-  //       let r = data[x * 4 + y * width * 4];
-  //       let g = data[x * 4 + y * width * 4 + 1];
-  //       let b = data[x * 4 + y * width * 4 + 2];
-  //       let a = data[x * 4 + y * width * 4 + 3];
-  //       print!("{}", if r > 0 || g > 0 || b > 0 || a > 0 {"X"} else {"_"});
-  //   }
-  //   println!("");
-  // }
 
   // on a matrix highlight cells (dots) that correspond to the outlines
   // in other words: picture to matrix
   let mut dots = Vec::new();
 
-  let threshold = 30.0;
   let box_size = height / 7;
   println!("box size {box_size}");
   for i_x in 0..(width / box_size) {
     let mut week: [u8; 7] = [0; 7];
-    for i_y in 0..(height / box_size) {
-      let mut total_box_color: u64 = 0;
+    for (i_y, day) in week.iter_mut().enumerate() {
+      let mut total_box_coverage: f64 = 0.0;
       for y in i_y * box_size..(i_y + 1) * box_size{
         for x in i_x * box_size..(i_x + 1) * box_size {
-          // print!("{}", x * 4 + y * width * 4);
-          let r = data[x * 4 + y * width * 4];
-          let g = data[x * 4 + y * width * 4 + 1];
-          let b = data[x * 4 + y * width * 4 + 2];
-          let a = data[x * 4 + y * width * 4 + 3];
-          total_box_color += (r + g + b + a) as u64
+          let offset = x * 4 + y * stride;
+          let pixel = u32::from_ne_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+          total_box_coverage += pixel_coverage(pixel, mode);
         }
       }
-      // print!(" {} ", if total_box_color as f64 / (4 * box_size * box_size) as f64 > 1.0 {"X"} else {"_"});
-      // print!("{}", if total_box_color as f64 / (4 * box_size * box_size) as f64 > threshold {"X"} else {"_"});
-      let busy_day = if total_box_color as f64 / (4 * box_size * box_size) as f64 > threshold {1} else {0};
-      week[i_y] = busy_day;
+      let coverage = total_box_coverage / (box_size * box_size) as f64;
+      *day = coverage_to_level(coverage);
     }
-    // println!("");
     dots.push(week);
   }
 
   dots
 }
 
+// boxes covered less than this are treated as anti-aliasing noise around the glyphs
+const MIN_COVERAGE: f64 = 0.1;
+
+// average box coverage (0.0 - 1.0) -> one of the four contribution levels, 0 means no commits
+fn coverage_to_level(coverage: f64) -> u8 {
+  if coverage < MIN_COVERAGE {
+    return 0;
+  }
+  (coverage * 4.0).ceil().clamp(1.0, 4.0) as u8
+}
+
 // Convert tutorial from C to Rust
 // https://cairographics.org/tutorial/#L1understandingtext
 
@@ -108,7 +171,7 @@ impl Empty<TextExtents> for TextExtents {
   }
 }
 
-fn text_to_surface(text: String) -> ImageSurface {
+fn text_to_surface(text: &str, font: &str) -> ImageSurface {
   // output size
   let width = 450;
   let height = 60;
@@ -122,8 +185,8 @@ fn text_to_surface(text: String) -> ImageSurface {
   let context_ptr = context.to_raw_none();
   // println!("reference counter c {}", unsafe {cairo_surface_get_reference_count(surface_ptr)});
 
-  let font_family = b"Source Code Pro\0".as_ptr();
-  unsafe {cairo_select_font_face(context_ptr, font_family as *const i8,
+  let font_family = CString::new(font).expect("font name without NUL bytes");
+  unsafe {cairo_select_font_face(context_ptr, font_family.as_ptr(),
     FONT_SLANT_NORMAL,
     FONT_WEIGHT_BOLD)};
 
@@ -134,8 +197,9 @@ fn text_to_surface(text: String) -> ImageSurface {
   unsafe {cairo_font_extents(context_ptr, &mut fe)};
 
   let mut te: TextExtents = TextExtents::empty();
-  let chars = text.as_bytes().as_ptr();
-  unsafe {cairo_text_extents(context_ptr, chars as *const i8, &mut te)};
+  let c_text = CString::new(text).expect("text without NUL bytes");
+  let chars = c_text.as_ptr();
+  unsafe {cairo_text_extents(context_ptr, chars, &mut te)};
   
   let x = te.x_bearing;
   let y = fe.height - fe.descent - 15.0;
@@ -143,7 +207,7 @@ fn text_to_surface(text: String) -> ImageSurface {
   
   unsafe {cairo_move_to(context_ptr, x, y)};
 
-  unsafe {cairo_show_text(context_ptr, chars as *const i8)};
+  unsafe {cairo_show_text(context_ptr, chars)};
 
   surface
 }
@@ -154,8 +218,8 @@ fn save_surface_as_png(surface: &ImageSurface) {
 }
 
 #[allow(dead_code)]
-fn text_to_png(text: String) {
-  let surface = text_to_surface(text);
+fn text_to_png(text: &str, font: &str) {
+  let surface = text_to_surface(text, font);
   save_surface_as_png(&surface);
 }
 
@@ -169,8 +233,8 @@ fn test_cairo() {
     i32::from(Format::ARgb32), width, height)};
   let context = unsafe {cairo_create(surface)};
 
-  let font_family = b"Source Code Pro\0".as_ptr();
-  unsafe {cairo_select_font_face(context, font_family as *const i8,
+  let font_family = c"Source Code Pro".as_ptr();
+  unsafe {cairo_select_font_face(context, font_family,
     FONT_SLANT_NORMAL,
     FONT_WEIGHT_BOLD)};
 
@@ -196,11 +260,9 @@ fn test_cairo() {
   }
 
   let result = unsafe {ImageSurface::from_raw_full(surface)};
-  if result.is_ok() {
-    let image = result.expect("image surface wow");
+  if let Ok(image) = result {
     let file_result = File::create("text.png");
-    if file_result.is_ok() {
-      let mut file = file_result.expect("file is open");
+    if let Ok(mut file) = file_result {
       let write_result = image.write_to_png(&mut file);
       if write_result.is_err() {
         write_result.err();
@@ -208,3 +270,53 @@ fn test_cairo() {
     }
   }
 }
+
+#[test]
+fn test_pixel_coverage() {
+  let white = 0xffff_ffff;
+  let black = 0xff00_0000;
+  let half_black = 0x8000_0000;
+  let approx = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+  assert!(approx(pixel_coverage(white, ImageMode::Alpha), 1.0));
+  assert!(approx(pixel_coverage(black, ImageMode::Alpha), 1.0));
+  assert!(approx(pixel_coverage(0, ImageMode::Alpha), 0.0));
+
+  assert!(approx(pixel_coverage(white, ImageMode::Luminance), 1.0));
+  assert!(approx(pixel_coverage(black, ImageMode::Luminance), 0.0));
+
+  assert!(approx(pixel_coverage(white, ImageMode::Inverted), 0.0));
+  assert!(approx(pixel_coverage(black, ImageMode::Inverted), 1.0));
+  assert!(approx(pixel_coverage(half_black, ImageMode::Inverted), 128.0 / 255.0));
+  // a transparent pixel is white paper, not ink
+  assert!(approx(pixel_coverage(0, ImageMode::Inverted), 0.0));
+}
+
+#[test]
+fn test_image_to_dots() {
+  // a 3x7 picture: a black column, a transparent one and a black dot on Wednesday
+  let image = ImageSurface::create(Format::ARgb32, 3, 7).unwrap();
+  {
+    let context = Context::new(&image).unwrap();
+    context.set_source_rgb(0.0, 0.0, 0.0);
+    context.rectangle(0.0, 0.0, 1.0, 7.0);
+    context.rectangle(2.0, 3.0, 1.0, 1.0);
+    context.fill().unwrap();
+  }
+
+  assert_eq!(picture_to_dots(&image, ImageMode::Alpha).unwrap(), vec![[4; 7], [0; 7], [0, 0, 0, 4, 0, 0, 0]]);
+  assert_eq!(picture_to_dots(&image, ImageMode::Luminance).unwrap(), vec![[0; 7]; 3]);
+  assert_eq!(picture_to_dots(&image, ImageMode::Inverted).unwrap(), vec![[4; 7], [0; 7], [0, 0, 0, 4, 0, 0, 0]]);
+}
+
+#[test]
+fn test_coverage_to_level() {
+  assert_eq!(coverage_to_level(0.0), 0);
+  // anti-aliasing noise stays empty
+  assert_eq!(coverage_to_level(MIN_COVERAGE - 1e-9), 0);
+  assert_eq!(coverage_to_level(MIN_COVERAGE), 1);
+  assert_eq!(coverage_to_level(0.25), 1);
+  assert_eq!(coverage_to_level(0.26), 2);
+  assert_eq!(coverage_to_level(0.75), 3);
+  assert_eq!(coverage_to_level(1.0), 4);
+}
@@ -0,0 +1,77 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::fmt::Write;
+
+// Looks of the contribution calendar on a GitHub profile (light theme)
+const CELL_SIZE: usize = 10;
+const CELL_STEP: usize = 13;
+const LEFT_MARGIN: usize = 30;
+const TOP_MARGIN: usize = 20;
+const COLORS: [&str; 5] = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+const TEXT_COLOR: &str = "#57606a";
+const DAY_LABELS: [(usize, &str); 3] = [(1, "Mon"), (3, "Wed"), (5, "Fri")];
+
+// GitHub splits the range between zero and the busiest day into four quartiles
+pub fn color_level(count: u32, max_count: u32) -> usize {
+  if count == 0 || max_count == 0 {
+    return 0;
+  }
+  let level = (4 * count).div_ceil(max_count) as usize;
+  level.clamp(1, 4)
+}
+
+pub fn dots_to_svg(commits: &[[u32; 7]], start_date: NaiveDate) -> String {
+  let max_count = commits
+    .iter()
+    .flat_map(|week| week.iter())
+    .copied()
+    .max()
+    .unwrap_or(0);
+  let width = LEFT_MARGIN + commits.len() * CELL_STEP;
+  let height = TOP_MARGIN + 7 * CELL_STEP;
+
+  let mut svg = String::new();
+  writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="-apple-system, Helvetica, Arial, sans-serif" font-size="9" fill="{TEXT_COLOR}">"#).unwrap();
+
+  // month name above the first week that starts in that month
+  let mut last_label_column: Option<usize> = None;
+  for column in 0..commits.len() {
+    let sunday = start_date + Duration::weeks(column as i64);
+    let previous = sunday - Duration::weeks(1);
+    let new_month = column == 0 || previous.month() != sunday.month();
+    // labels need about three columns of room, otherwise they overlap
+    let has_room = last_label_column.is_none_or(|last| column - last >= 3);
+    if new_month && has_room {
+      let x = LEFT_MARGIN + column * CELL_STEP;
+      writeln!(svg, r#"  <text x="{x}" y="{}">{}</text>"#, TOP_MARGIN - 8, sunday.format("%b")).unwrap();
+      last_label_column = Some(column);
+    }
+  }
+
+  for (row, label) in DAY_LABELS {
+    let y = TOP_MARGIN + row * CELL_STEP + CELL_SIZE - 1;
+    writeln!(svg, r#"  <text x="0" y="{y}">{label}</text>"#).unwrap();
+  }
+
+  for (column, week) in commits.iter().enumerate() {
+    for (row, count) in week.iter().enumerate() {
+      let date = start_date + Duration::days((column * 7 + row) as i64);
+      let x = LEFT_MARGIN + column * CELL_STEP;
+      let y = TOP_MARGIN + row * CELL_STEP;
+      let color = COLORS[color_level(*count, max_count)];
+      writeln!(svg, r#"  <rect x="{x}" y="{y}" width="{CELL_SIZE}" height="{CELL_SIZE}" rx="2" ry="2" fill="{color}"><title>{count} contributions on {}</title></rect>"#, date.format("%Y-%m-%d")).unwrap();
+    }
+  }
+
+  svg.push_str("</svg>\n");
+  svg
+}
+
+#[test]
+fn test_color_level_quartiles() {
+  assert_eq!(color_level(0, 8), 0);
+  assert_eq!(color_level(1, 8), 1);
+  assert_eq!(color_level(2, 8), 1);
+  assert_eq!(color_level(3, 8), 2);
+  assert_eq!(color_level(6, 8), 3);
+  assert_eq!(color_level(8, 8), 4);
+}
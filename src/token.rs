@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+// Access tokens live in the user config directory, not in the directory the tool runs from:
+// ~/.config/activity-brush/tokens.json, readable only by the owner
+
+// takes priority over the stored tokens and is never written to disk
+pub const TOKEN_ENV: &str = "ACTIVITY_BRUSH_TOKEN";
+
+// host -> user login -> token
+type Tokens = BTreeMap<String, BTreeMap<String, String>>;
+
+fn tokens_path() -> Result<PathBuf> {
+  let config_dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+    Some(dir) => PathBuf::from(dir),
+    None => env::var_os("HOME")
+      .map(|home| PathBuf::from(home).join(".config"))
+      .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "neither XDG_CONFIG_HOME nor HOME is set"))?,
+  };
+  Ok(config_dir.join("activity-brush").join("tokens.json"))
+}
+
+fn read_tokens(path: &Path) -> Result<Tokens> {
+  match fs::read_to_string(path) {
+    Ok(content) => serde_json::from_str(&content).map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+    Err(e) if e.kind() == ErrorKind::NotFound => Ok(Tokens::new()),
+    Err(e) => Err(e),
+  }
+}
+
+fn write_tokens(path: &Path, tokens: &Tokens) -> Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  let mut options = OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    options.mode(0o600);
+    // the mode above applies only to a new file
+    if path.exists() {
+      fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+  }
+  let mut file = options.open(path)?;
+  let content = serde_json::to_string_pretty(tokens).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+  file.write_all(content.as_bytes())
+}
+
+pub fn env_token() -> Option<String> {
+  env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty())
+}
+
+pub fn stored_token(host: &str) -> Result<Option<String>> {
+  let tokens = read_tokens(&tokens_path()?)?;
+  Ok(tokens.get(host).and_then(|users| users.values().next()).cloned())
+}
+
+pub fn store_token(host: &str, login: &str, token: &str) -> Result<()> {
+  let path = tokens_path()?;
+  let mut tokens = read_tokens(&path)?;
+  tokens
+    .entry(host.to_string())
+    .or_default()
+    .insert(login.to_string(), token.to_string());
+  write_tokens(&path, &tokens)?;
+  println!("Token for '{login}' on {host} saved to {}", path.display());
+  Ok(())
+}
+
+// drops a token that does not work anymore, whichever user it belonged to
+pub fn remove_token(host: &str, token: &str) -> Result<()> {
+  let path = tokens_path()?;
+  let mut tokens = read_tokens(&path)?;
+  if let Some(users) = tokens.get_mut(host) {
+    users.retain(|_login, stored| stored != token);
+  }
+  write_tokens(&path, &tokens)
+}
+
+#[test]
+fn test_tokens_file() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("activity-brush").join("tokens.json");
+  assert!(read_tokens(&path).unwrap().is_empty());
+
+  let mut tokens = Tokens::new();
+  tokens.entry("github.com".to_string()).or_default().insert("octocat".to_string(), "secret".to_string());
+  write_tokens(&path, &tokens).unwrap();
+  assert_eq!(read_tokens(&path).unwrap(), tokens);
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+}
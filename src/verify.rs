@@ -0,0 +1,141 @@
+use chrono::{Duration, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::thread;
+use std::time;
+
+use crate::error::{Error, Result};
+use crate::forge::{fetch_contributions, Account, Contributions, Forge};
+
+// the calendar is recounted a while after the push
+const ATTEMPTS: u32 = 6;
+const RETRY_DELAY: time::Duration = time::Duration::from_secs(20);
+
+// After the push the calendar of the profile is compared with the plan, cell by cell.
+// Real activity counted before the painting (the baseline) is subtracted first
+
+#[derive(Debug, PartialEq)]
+pub enum Mismatch {
+  // nothing arrived on a painted day
+  Missing { date: NaiveDate, expected: u32 },
+  // the commits of the day were counted on the previous or the next one, a timezone problem
+  Shifted { date: NaiveDate, to: NaiveDate },
+  // some commits arrived, but not as many as planned
+  Intensity { date: NaiveDate, expected: u32, actual: u32 },
+  // commits on a day that should stay empty
+  Unexpected { date: NaiveDate, actual: u32 },
+}
+
+impl fmt::Display for Mismatch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Mismatch::Missing { date, expected } => write!(f, "{date}: missing, expected {expected} commits"),
+      Mismatch::Shifted { date, to } => write!(f, "{date}: shifted to {to}"),
+      Mismatch::Intensity { date, expected, actual } => write!(f, "{date}: {actual} commits instead of {expected}"),
+      Mismatch::Unexpected { date, actual } => write!(f, "{date}: {actual} commits on an empty day"),
+    }
+  }
+}
+
+// commits expected on every day of the pattern, with an empty day on each side to catch shifts
+pub fn planned_contributions(start_date: NaiveDate, commits: &[[u32; 7]]) -> Contributions {
+  let mut planned = Contributions::new();
+  planned.insert(start_date - Duration::days(1), 0);
+  for (column, week) in commits.iter().enumerate() {
+    for (week_day, commits) in week.iter().enumerate() {
+      planned.insert(start_date + Duration::days((7 * column + week_day) as i64), *commits);
+    }
+  }
+  if let Some(last) = planned.keys().next_back().copied() {
+    planned.insert(last + Duration::days(1), 0);
+  }
+  planned
+}
+
+// first and last day that have to be fetched
+pub fn date_range(planned: &Contributions) -> Option<(NaiveDate, NaiveDate)> {
+  Some((*planned.keys().next()?, *planned.keys().next_back()?))
+}
+
+pub fn compare(planned: &Contributions, baseline: &Contributions, current: &Contributions) -> Vec<Mismatch> {
+  let painted = |date: &NaiveDate| {
+    let before = baseline.get(date).copied().unwrap_or(0);
+    current.get(date).copied().unwrap_or(0).saturating_sub(before)
+  };
+
+  // a day whose commits show up in full on a neighbour was shifted, the neighbour is explained by it
+  let mut shifts = BTreeMap::new();
+  for (date, expected) in planned {
+    if *expected == 0 || painted(date) == *expected {
+      continue;
+    }
+    let neighbours = [*date + Duration::days(1), *date - Duration::days(1)];
+    if let Some(to) = neighbours.into_iter().find(|day| planned.contains_key(day) && painted(day) == *expected) {
+      shifts.insert(*date, to);
+    }
+  }
+  let explained: BTreeSet<&NaiveDate> = shifts.values().collect();
+
+  let mut mismatches = Vec::new();
+  for (date, expected) in planned {
+    if let Some(to) = shifts.get(date) {
+      mismatches.push(Mismatch::Shifted { date: *date, to: *to });
+      continue;
+    }
+    match (*expected, painted(date)) {
+      (expected, actual) if expected == actual => {},
+      _ if explained.contains(date) => {},
+      (0, actual) => mismatches.push(Mismatch::Unexpected { date: *date, actual }),
+      (expected, 0) => mismatches.push(Mismatch::Missing { date: *date, expected }),
+      (expected, actual) => mismatches.push(Mismatch::Intensity { date: *date, expected, actual }),
+    }
+  }
+  mismatches
+}
+
+// `baseline` is the calendar fetched before painting
+pub fn verify_painting(forge: &dyn Forge, account: &Account, planned: &Contributions, baseline: &Contributions) -> Result<()> {
+  let (from, to) = match date_range(planned) {
+    Some(range) => range,
+    None => return Ok(()),
+  };
+  let mut attempt = 1;
+  loop {
+    let current = fetch_contributions(forge, account, from, to)?.unwrap_or_default();
+    let mismatches = compare(planned, baseline, &current);
+    if mismatches.is_empty() {
+      println!("The calendar shows the painting");
+      return Ok(());
+    }
+    if attempt == ATTEMPTS {
+      for mismatch in &mismatches {
+        println!("  {mismatch}");
+      }
+      return Err(Error::Verify(format!("{} of {} days differ", mismatches.len(), planned.len())));
+    }
+    println!("{} days differ, checking again in {}s", mismatches.len(), RETRY_DELAY.as_secs());
+    thread::sleep(RETRY_DELAY);
+    attempt += 1;
+  }
+}
+
+#[test]
+fn test_compare_classifies_cells() {
+  let start = NaiveDate::from_ymd(2022, 1, 2);
+  let planned = planned_contributions(start, &[[0, 1, 2, 0, 0, 0, 0], [0, 0, 0, 1, 0, 2, 0]]);
+  assert_eq!(date_range(&planned), Some((start - Duration::days(1), start + Duration::days(14))));
+  let day = |n: i64| start + Duration::days(n);
+
+  // a real commit on the first painted day is not part of the painting
+  let baseline = Contributions::from([(day(1), 5)]);
+  let exact = Contributions::from([(day(1), 6), (day(2), 2), (day(10), 1), (day(12), 2)]);
+  assert!(compare(&planned, &baseline, &exact).is_empty());
+
+  let wrong = Contributions::from([(day(1), 5), (day(2), 3), (day(11), 1), (day(12), 2), (day(13), 4)]);
+  assert_eq!(compare(&planned, &baseline, &wrong), vec![
+    Mismatch::Missing { date: day(1), expected: 1 },
+    Mismatch::Intensity { date: day(2), expected: 2, actual: 3 },
+    Mismatch::Shifted { date: day(10), to: day(11) },
+    Mismatch::Unexpected { date: day(13), actual: 4 },
+  ]);
+}